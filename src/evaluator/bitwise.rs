@@ -0,0 +1,217 @@
+// Bitwise operations on `Int`s, seen as 64-bit two's complement integers.
+// Unlike arithmetic, they do not promote to big integers: a result that does
+// not fit is an `IntOverflow` error.
+
+use super::builtins::{Arity, Registry};
+use super::{Context, EvalError, Result};
+use crate::parser::{Atom, Expr, Num};
+use num_bigint::BigInt;
+use std::convert::TryFrom;
+
+pub(super) fn register(registry: &mut Registry) {
+    registry
+        .function("logand", Arity::AtLeast(0), logand)
+        .function("logior", Arity::AtLeast(0), logior)
+        .function("logxor", Arity::AtLeast(0), logxor)
+        .function("lognot", Arity::Exact(1), lognot)
+        .function("ash", Arity::Exact(2), ash)
+        .function("logcount", Arity::Exact(1), logcount)
+        .function("integer-length", Arity::Exact(1), integer_length)
+        .function("hex", Arity::Exact(1), hex)
+        .function("bin", Arity::Exact(1), bin);
+}
+
+fn fixnums(num: &[Num]) -> Result<Vec<i64>> {
+    num.iter()
+        .map(|x| match x {
+            Num::Int(i) => Ok(*i),
+            Num::Big(_) => Err(EvalError::IntOverflow),
+            _ => Err(EvalError::ShouldBeInteger),
+        })
+        .collect()
+}
+
+/// Fold the integer arguments with `op`, starting from `identity`.
+fn fold_bits(
+    ctx: &mut Context,
+    args: Vec<Expr>,
+    identity: i64,
+    op: fn(i64, i64) -> i64,
+) -> Result<Expr> {
+    ctx.with_numbers(args, |num| {
+        Ok(Num::Int(fixnums(num)?.into_iter().fold(identity, op)))
+    })
+}
+
+/// `(logand n...)`: bitwise and, -1 without arguments
+fn logand(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    fold_bits(ctx, args, -1, |a, b| a & b)
+}
+
+/// `(logior n...)`: bitwise inclusive or, 0 without arguments
+fn logior(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    fold_bits(ctx, args, 0, |a, b| a | b)
+}
+
+/// `(logxor n...)`: bitwise exclusive or, 0 without arguments
+fn logxor(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    fold_bits(ctx, args, 0, |a, b| a ^ b)
+}
+
+fn lognot(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    ctx.with_numbers(args, |num| Ok(Num::Int(!fixnums(num)?[0])))
+}
+
+/// `n` shifted left by `count` bits, or right if `count` is negative.
+fn shift(n: i64, count: i64) -> Result<i64> {
+    if count < 0 {
+        // Shifting right by 63 bits or more leaves only the sign
+        return Ok(n >> count.unsigned_abs().min(63));
+    }
+    let shifted = u32::try_from(count)
+        .ok()
+        .and_then(|bits| n.checked_shl(bits))
+        .filter(|shifted| shifted >> count == n);
+    match shifted {
+        Some(shifted) => Ok(shifted),
+        None if n == 0 => Ok(0),
+        None => Err(EvalError::IntOverflow),
+    }
+}
+
+/// `(ash n count)`: arithmetic shift of `n` by `count` bits, left if `count`
+/// is positive and right if it is negative
+fn ash(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    ctx.with_numbers(args, |num| {
+        let ints = fixnums(num)?;
+        shift(ints[0], ints[1]).map(Num::Int)
+    })
+}
+
+/// `(logcount n)`: number of bits set in a non-negative `n`, or cleared in
+/// a negative one
+fn logcount(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    ctx.with_numbers(args, |num| {
+        let n = fixnums(num)?[0];
+        let bits = if n < 0 { !n } else { n };
+        Ok(Num::Int(i64::from(bits.count_ones())))
+    })
+}
+
+/// `(integer-length n)`: number of bits needed for `n` in two's complement,
+/// without the sign bit
+fn integer_length(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    ctx.with_numbers(args, |num| {
+        let n = fixnums(num)?[0];
+        let bits = if n < 0 { !n } else { n };
+        Ok(Num::Int(i64::from(64 - bits.leading_zeros())))
+    })
+}
+
+/// An integer as a literal in `radix`, e.g. `#xFF` or `#b-101`.
+fn radix_literal(args: Vec<Expr>, prefix: &str, radix: u32) -> Result<Expr> {
+    let digits = match &args[0] {
+        Expr::Atomic(Atom::Number(Num::Int(i))) => BigInt::from(*i).to_str_radix(radix),
+        Expr::Atomic(Atom::Number(Num::Big(b))) => b.to_str_radix(radix),
+        Expr::Atomic(Atom::Number(_)) => return Err(EvalError::ShouldBeInteger),
+        _ => return Err(EvalError::ShouldBeNum),
+    };
+    Ok(Expr::Atomic(Atom::Quoted(format!(
+        "{}{}",
+        prefix,
+        digits.to_uppercase()
+    ))))
+}
+
+/// `(hex n)`: `n` as a hexadecimal literal string, e.g. `"#xFF"`
+fn hex(_: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    radix_literal(args, "#x", 16)
+}
+
+/// `(bin n)`: `n` as a binary literal string, e.g. `"#b101"`
+fn bin(_: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    radix_literal(args, "#b", 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::evaluator::{eval_str, show, Context, EvalError};
+
+    #[test]
+    fn should_combine_bits() {
+        let ctx = &mut Context::default();
+        assert_eq!(show(ctx, "(logand #b1100 #b1010)"), "8");
+        assert_eq!(show(ctx, "(logior #b1100 #b1010)"), "14");
+        assert_eq!(show(ctx, "(logxor #b1100 #b1010 #b0001)"), "7");
+        assert_eq!(show(ctx, "(logand)"), "-1");
+        assert_eq!(show(ctx, "(logior)"), "0");
+        assert_eq!(show(ctx, "(logand -1 #xFF)"), "255");
+        assert_eq!(show(ctx, "(lognot 0)"), "-1");
+        assert_eq!(show(ctx, "(lognot #xF)"), "-16");
+        assert!(matches!(
+            eval_str(ctx, "(logand 1 1.0)"),
+            Err(EvalError::ShouldBeInteger)
+        ));
+        assert!(matches!(
+            eval_str(ctx, "(logior 1 #x1_0000_0000_0000_0000)"),
+            Err(EvalError::IntOverflow)
+        ));
+    }
+
+    #[test]
+    fn should_shift_bits() {
+        let ctx = &mut Context::default();
+        assert_eq!(show(ctx, "(ash 1 10)"), "1024");
+        assert_eq!(show(ctx, "(ash -1 63)"), "-9223372036854775808");
+        assert_eq!(show(ctx, "(ash 1024 -3)"), "128");
+        assert_eq!(show(ctx, "(ash -5 -1)"), "-3");
+        assert_eq!(show(ctx, "(ash -5 -100)"), "-1");
+        assert_eq!(show(ctx, "(ash 5 -100)"), "0");
+        assert_eq!(show(ctx, "(ash 0 1000)"), "0");
+        assert!(matches!(
+            eval_str(ctx, "(ash 1 63)"),
+            Err(EvalError::IntOverflow)
+        ));
+        assert!(matches!(
+            eval_str(ctx, "(ash 3 62)"),
+            Err(EvalError::IntOverflow)
+        ));
+        assert!(matches!(
+            eval_str(ctx, "(ash 1 64)"),
+            Err(EvalError::IntOverflow)
+        ));
+    }
+
+    #[test]
+    fn should_count_bits() {
+        let ctx = &mut Context::default();
+        assert_eq!(show(ctx, "(logcount #b1011)"), "3");
+        assert_eq!(show(ctx, "(logcount -1)"), "0");
+        assert_eq!(show(ctx, "(logcount -8)"), "3");
+        assert_eq!(show(ctx, "(integer-length 0)"), "0");
+        assert_eq!(show(ctx, "(integer-length 255)"), "8");
+        assert_eq!(show(ctx, "(integer-length 256)"), "9");
+        assert_eq!(show(ctx, "(integer-length -1)"), "0");
+        assert_eq!(show(ctx, "(integer-length -129)"), "8");
+    }
+
+    #[test]
+    fn should_print_radix_literals() {
+        let ctx = &mut Context::default();
+        assert_eq!(show(ctx, "(hex 255)"), "\"#xFF\"");
+        assert_eq!(show(ctx, "(hex -31)"), "\"#x-1F\"");
+        assert_eq!(show(ctx, "(bin 5)"), "\"#b101\"");
+        assert_eq!(
+            show(ctx, "(hex 18446744073709551616)"),
+            "\"#x10000000000000000\""
+        );
+        assert!(matches!(
+            eval_str(ctx, "(hex 1/2)"),
+            Err(EvalError::ShouldBeInteger)
+        ));
+        assert_eq!(
+            crate::parser::parse_str("#x-1F").unwrap().to_string(),
+            "-31"
+        );
+    }
+}
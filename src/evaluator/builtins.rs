@@ -0,0 +1,132 @@
+use super::{bitwise, conditionals, math, variables, Context, EvalError, Result};
+use crate::parser::Expr;
+use std::collections::HashMap;
+
+/// Number of arguments a builtin accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arity {
+    Exact(usize),
+    AtLeast(usize),
+    /// Inclusive bounds
+    Between(usize, usize),
+}
+
+impl Arity {
+    pub fn check(self, got: usize) -> Result<()> {
+        match self {
+            Arity::Exact(exp) if got != exp => Err(EvalError::ArgumentNumber { exp, got }),
+            Arity::AtLeast(min) | Arity::Between(min, _) if got < min => {
+                Err(EvalError::TooFewArguments { min, got })
+            }
+            Arity::Between(_, max) if got > max => Err(EvalError::TooManyArguments { max, got }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// How a builtin receives its arguments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// Arguments are evaluated before the handler is called.
+    Function,
+    /// Arguments are passed as written, the handler decides what to evaluate.
+    SpecialForm,
+}
+
+pub type Handler = fn(&mut Context, Vec<Expr>) -> Result<Expr>;
+
+/// A builtin implemented natively.
+#[derive(Debug, Clone, Copy)]
+pub struct Builtin {
+    pub name: &'static str,
+    pub arity: Arity,
+    pub kind: Kind,
+    pub handler: Handler,
+}
+
+/// Builtins of a context, by name.
+#[derive(Debug, Clone, Default)]
+pub struct Registry {
+    builtins: HashMap<&'static str, Builtin>,
+}
+
+impl Registry {
+    /// Register a builtin, replacing any previous one with the same name.
+    pub fn insert(&mut self, builtin: Builtin) -> &mut Self {
+        self.builtins.insert(builtin.name, builtin);
+        self
+    }
+
+    pub fn function(&mut self, name: &'static str, arity: Arity, handler: Handler) -> &mut Self {
+        self.insert(Builtin {
+            name,
+            arity,
+            kind: Kind::Function,
+            handler,
+        })
+    }
+
+    pub fn special_form(
+        &mut self,
+        name: &'static str,
+        arity: Arity,
+        handler: Handler,
+    ) -> &mut Self {
+        self.insert(Builtin {
+            name,
+            arity,
+            kind: Kind::SpecialForm,
+            handler,
+        })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Builtin> {
+        self.builtins.get(name)
+    }
+}
+
+/// The builtins every context starts with.
+pub fn standard() -> Registry {
+    let mut registry = Registry::default();
+    registry
+        .function("+", Arity::AtLeast(0), Context::add)
+        .function("add", Arity::AtLeast(0), Context::add)
+        .function("-", Arity::AtLeast(1), Context::sub)
+        .function("sub", Arity::AtLeast(1), Context::sub)
+        .function("*", Arity::AtLeast(0), Context::mul)
+        .function("mul", Arity::AtLeast(0), Context::mul)
+        .function("/", Arity::AtLeast(1), Context::div)
+        .function("div", Arity::AtLeast(1), Context::div)
+        .function("numerator", Arity::Exact(1), Context::numerator)
+        .function("denominator", Arity::Exact(1), Context::denominator)
+        .function("real-part", Arity::Exact(1), Context::real_part)
+        .function("imag-part", Arity::Exact(1), Context::imag_part)
+        .function("magnitude", Arity::Exact(1), Context::magnitude)
+        .function("angle", Arity::Exact(1), Context::angle)
+        .function("conjugate", Arity::Exact(1), Context::conjugate)
+        .function("decimal", Arity::Exact(1), Context::decimal)
+        .function("double", Arity::Exact(1), Context::double)
+        .function(
+            "round-decimal",
+            Arity::Between(2, 3),
+            Context::round_decimal,
+        )
+        .function("nth", Arity::Exact(2), Context::nth)
+        .function("list", Arity::AtLeast(0), Context::list)
+        .function("eval", Arity::Exact(1), Context::eval_builtin)
+        .function("car", Arity::Exact(1), Context::car)
+        .function("null", Arity::Exact(1), Context::null)
+        .special_form("quote", Arity::Exact(1), Context::quote)
+        .special_form("defun", Arity::AtLeast(2), Context::defun)
+        .special_form("lambda", Arity::AtLeast(1), Context::lambda)
+        .function("funcall", Arity::AtLeast(1), Context::funcall)
+        .function("apply", Arity::AtLeast(1), Context::apply_list)
+        .function("mapcar", Arity::Exact(2), Context::mapcar)
+        .special_form("flet", Arity::AtLeast(1), Context::flet)
+        .special_form("builtin", Arity::AtLeast(1), Context::call_builtin);
+    math::register(&mut registry);
+    bitwise::register(&mut registry);
+    variables::register(&mut registry);
+    conditionals::register(&mut registry);
+    registry
+}
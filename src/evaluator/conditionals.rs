@@ -0,0 +1,189 @@
+// Conditional forms. They all test values with `truthy`, so nil and the
+// empty list are false and anything else is true, and only evaluate the
+// forms they need.
+
+use super::builtins::{Arity, Registry};
+use super::{boolean, truthy, Context, EvalError, Result};
+use crate::parser::Expr;
+
+pub(super) fn register(registry: &mut Registry) {
+    registry
+        .special_form("if", Arity::AtLeast(2), if_form)
+        .special_form("cond", Arity::AtLeast(0), cond)
+        .special_form("when", Arity::AtLeast(1), when)
+        .special_form("unless", Arity::AtLeast(1), unless)
+        .special_form("and", Arity::AtLeast(0), and)
+        .special_form("or", Arity::AtLeast(0), or)
+        .function("not", Arity::Exact(1), not);
+}
+
+/// `(if test then else...)`: `then` if `test` is true, the `else` forms
+/// otherwise
+fn if_form(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    if truthy(&ctx.eval_ast(&args[0])?) {
+        ctx.eval_ast(&args[1])
+    } else {
+        ctx.progn(&args[2..])
+    }
+}
+
+/// `(cond (test body...)...)`: the body of the first clause whose test is
+/// true, or the value of the test if the body is empty. Nil if no test is
+/// true.
+fn cond(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    for clause in &args {
+        let (test, body) = match clause {
+            Expr::List(clause) => match clause.split_first() {
+                Some(split) => split,
+                None => return Err(EvalError::InvalidSyntax),
+            },
+            _ => return Err(EvalError::InvalidSyntax),
+        };
+        let value = ctx.eval_ast(test)?;
+        if truthy(&value) {
+            return if body.is_empty() {
+                Ok(value)
+            } else {
+                ctx.progn(body)
+            };
+        }
+    }
+    Ok(boolean(false))
+}
+
+/// `(when test body...)`: the body if `test` is true, nil otherwise
+fn when(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    if truthy(&ctx.eval_ast(&args[0])?) {
+        ctx.progn(&args[1..])
+    } else {
+        Ok(boolean(false))
+    }
+}
+
+/// `(unless test body...)`: the body if `test` is false, nil otherwise
+fn unless(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    if truthy(&ctx.eval_ast(&args[0])?) {
+        Ok(boolean(false))
+    } else {
+        ctx.progn(&args[1..])
+    }
+}
+
+/// `(and forms...)`: nil as soon as a form is false, the value of the last
+/// one otherwise, t without forms
+fn and(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    let mut res = boolean(true);
+    for form in &args {
+        res = ctx.eval_ast(form)?;
+        if !truthy(&res) {
+            return Ok(boolean(false));
+        }
+    }
+    Ok(res)
+}
+
+/// `(or forms...)`: the value of the first true form, nil if there is none
+fn or(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    for form in &args {
+        let value = ctx.eval_ast(form)?;
+        if truthy(&value) {
+            return Ok(value);
+        }
+    }
+    Ok(boolean(false))
+}
+
+/// `(not x)`: t if `x` is false, nil otherwise
+fn not(_: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    Ok(boolean(!truthy(&args[0])))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::evaluator::{eval_str, show, Context, EvalError, MAX_DEPTH, STACK_SIZE};
+
+    #[test]
+    fn should_branch() {
+        let ctx = &mut Context::default();
+        assert_eq!(show(ctx, "(if t 1 2)"), "1");
+        assert_eq!(show(ctx, "(if nil 1 2)"), "2");
+        assert_eq!(show(ctx, "(if () 1 2 3)"), "3");
+        assert_eq!(show(ctx, "(if '(nil) 1 2)"), "1");
+        assert_eq!(show(ctx, "(if 0 1 2)"), "1");
+        assert_eq!(show(ctx, "(if nil 1)"), "nil");
+        // The branch not taken is never evaluated
+        assert_eq!(show(ctx, "(if t 1 undefined)"), "1");
+        assert_eq!(show(ctx, "(if nil undefined 2)"), "2");
+        assert_eq!(show(ctx, "(when (null nil) 1 2)"), "2");
+        assert_eq!(show(ctx, "(when nil undefined)"), "nil");
+        assert_eq!(show(ctx, "(unless '() 1 2)"), "2");
+        assert_eq!(show(ctx, "(unless t undefined)"), "nil");
+    }
+
+    #[test]
+    fn should_pick_a_clause() {
+        let ctx = &mut Context::default();
+        assert_eq!(show(ctx, "(cond (nil 1) ((car '(2))) (t undefined))"), "2");
+        assert_eq!(show(ctx, "(cond ((list) 1) (t 2 3))"), "3");
+        assert_eq!(show(ctx, "(cond (nil 1))"), "nil");
+        assert_eq!(show(ctx, "(cond)"), "nil");
+        assert!(matches!(
+            eval_str(ctx, "(cond (nil 1) ())"),
+            Err(EvalError::InvalidSyntax)
+        ));
+        assert!(matches!(
+            eval_str(ctx, "(cond t)"),
+            Err(EvalError::InvalidSyntax)
+        ));
+    }
+
+    #[test]
+    fn should_short_circuit() {
+        let ctx = &mut Context::default();
+        assert_eq!(show(ctx, "(and)"), "t");
+        assert_eq!(show(ctx, "(and 1 2 3)"), "3");
+        assert_eq!(show(ctx, "(and 1 () undefined)"), "nil");
+        assert_eq!(show(ctx, "(or)"), "nil");
+        assert_eq!(show(ctx, "(or nil () 'a undefined)"), "a");
+        assert_eq!(show(ctx, "(or nil ())"), "nil");
+        assert_eq!(show(ctx, "(not nil)"), "t");
+        assert_eq!(show(ctx, "(not ())"), "t");
+        assert_eq!(show(ctx, "(not 0)"), "nil");
+    }
+
+    #[test]
+    fn should_recurse_to_a_base_case() {
+        let ctx = &mut Context::default();
+        // `(nth n '(t))` is true only for 0
+        eval_str(
+            ctx,
+            "(defun fact (n) (if (nth n '(t)) 1 (* n (fact (- n 1)))))",
+        )
+        .unwrap();
+        assert_eq!(show(ctx, "(fact 25)"), "15511210043330985984000000");
+        eval_str(
+            ctx,
+            "(defun count-down (n) (cond ((nth n '(t)) '(liftoff)) (t (list n (count-down (- n 1))))))",
+        )
+        .unwrap();
+        assert_eq!(show(ctx, "(count-down 2)"), "(2 (1 (liftoff)))");
+    }
+
+    #[test]
+    fn should_stop_runaway_recursion() {
+        // On a stack as large as the interpreter's
+        let thread = std::thread::Builder::new().stack_size(STACK_SIZE);
+        let test = thread.spawn(|| {
+            let ctx = &mut Context::default();
+            eval_str(ctx, "(defun down (n) (if (nth n '(t)) 0 (down (- n 1))))").unwrap();
+            assert_eq!(show(ctx, "(down 2000)"), "0");
+            assert!(matches!(
+                eval_str(ctx, "(down 100000)"),
+                Err(EvalError::DepthExceeded { max: MAX_DEPTH })
+            ));
+            // Unwinding from the error restores the depth
+            assert_eq!(show(ctx, "(down 2000)"), "0");
+        });
+        test.unwrap().join().unwrap();
+    }
+}
@@ -1,66 +1,57 @@
-use super::{Context, EvalError, Function, Result};
+use super::builtins::{Builtin, Kind};
+use super::env::Env;
+use super::{
+    as_list, boolean, code, datum, numeric, truthy, Callable, Context, EvalError, Function, Result,
+    MAX_DEPTH,
+};
 use crate::parser::Expr::Atomic;
 use crate::parser::Expr::Qexpr;
-use crate::parser::{Atom, Bool, Expr, Num, Ops};
-use std::collections::HashMap;
+use crate::parser::{Atom, Bool, Expr, Num};
+use std::convert::TryFrom;
+use std::rc::Rc;
 
 impl Context {
-    fn get_funcs(&self) -> &HashMap<String, Function> {
-        &self.funcs
+    #[cfg(test)]
+    fn add_var(&mut self, name: &str, var: Expr) {
+        self.globals.define_var(name, var);
     }
 
-    fn add_var(&mut self, name: &str, var: Expr) {
-        self.vars.insert(name.to_owned(), var);
+    /// Evaluate with `env` as the innermost frame, then restore the current
+    /// one.
+    pub(super) fn with_env<T>(&mut self, env: Rc<Env>, f: impl FnOnce(&mut Self) -> T) -> T {
+        let outer = std::mem::replace(&mut self.env, env);
+        let res = f(self);
+        self.env = outer;
+        res
     }
 
-    fn get_function_from_name(&self, name: &str, args: &[Expr]) -> Result<Function> {
-        let fun_res = self.get_funcs().get(name);
-        match fun_res {
-            Some(fun) => {
-                if args.len() != fun.args.len() {
-                    Err(EvalError::ArgumentNumber {
-                        exp: fun.args.len(),
-                        got: args.len(),
-                    })
-                } else {
-                    Ok(fun.clone())
-                }
-            }
-            None => Err(EvalError::VoidFunction {
-                name: name.to_string(),
-            }),
-        }
+    /// Resolve a name in the function namespace. Local function bindings
+    /// shadow global definitions, which shadow builtins.
+    fn lookup_function(&self, name: &str) -> Option<Callable> {
+        self.env
+            .lookup_function(name)
+            .map(Callable::User)
+            .or_else(|| self.builtins.get(name).map(|b| Callable::Builtin(*b)))
     }
 
     fn apply(&mut self, function: &Expr, args: Vec<Expr>) -> Result<Expr> {
         match function {
-            Atomic(Atom::Name(name)) => {
-                let fun = self.get_function_from_name(name, &args)?;
-                // Add the local variables to the context
-                for (i, arg_name) in fun.args.iter().enumerate() {
-                    let arg = args[i].clone();
-                    self.add_var(arg_name, arg);
+            Atomic(Atom::Name(name)) | Atomic(Atom::Symbol(name)) => {
+                match self.lookup_function(name) {
+                    Some(Callable::Builtin(builtin)) => self.apply_builtin(builtin, args),
+                    Some(Callable::User(fun)) => self.apply_function(&fun, args),
+                    None => Err(EvalError::VoidFunction {
+                        name: name.to_string(),
+                    }),
                 }
-                let res = self.eval_ast(&fun.body);
-                // Pop local variables from the context
-                for arg_name in fun.args.iter() {
-                    self.vars.remove(&arg_name.to_string());
-                }
-                res
             }
-            Atomic(Atom::Op(op)) => match op {
-                Ops::Add => self.add(args),
-                Ops::Sub => self.sub(args),
-                Ops::Mul => self.mul(args),
-                Ops::Div => self.div(args),
-                Ops::Defun => self.defun(args),
-                Ops::Nth => self.nth(args),
-                Ops::List => self.list(args),
-                Ops::Eval => self.eval_builtin(args),
-                Ops::Car => self.car(args),
-                // Ops::Map => self.map(args),
-                _ => Err(EvalError::Unimplemented {
-                    name: format!("{:?}", op),
+            // A function value, as in code built by `eval` or a quasiquote
+            Expr::Function(fun) => self.apply_function(fun, args),
+            // A lambda form, or any form evaluating to a function
+            Expr::List(_) => match self.eval_ast(function)? {
+                Expr::Function(fun) => self.apply_function(&fun, args),
+                other => Err(EvalError::InvalidFunction {
+                    function: other.to_string(),
                 }),
             },
             _ => Err(EvalError::InvalidFunction {
@@ -69,43 +60,153 @@ impl Context {
         }
     }
 
-    pub fn eval_ast(&mut self, ast: &Expr) -> Result<Expr> {
-        match ast {
-            Atomic(Atom::Name(name)) => match self.vars.get(name.as_str()) {
-                Some(var) => {
-                    let var = var.clone();
-                    self.eval_ast(&var)
-                }
-                None => Err(EvalError::VoidVariable {
+    fn apply_function(&mut self, fun: &Rc<Function>, args: Vec<Expr>) -> Result<Expr> {
+        if args.len() != fun.args.len() {
+            return Err(EvalError::ArgumentNumber {
+                exp: fun.args.len(),
+                got: args.len(),
+            });
+        }
+        // Arguments are evaluated once, left to right, in the caller's
+        // environment, and only when their number is right
+        let values = args
+            .iter()
+            .map(|arg| self.eval_ast(arg))
+            .collect::<Result<Vec<Expr>>>()?;
+        self.call_function(fun, values)
+    }
+
+    /// Call a user function on argument values. They are bound in a new
+    /// frame of the environment the function is defined in, so the body
+    /// cannot see the caller's locals.
+    fn call_function(&mut self, fun: &Rc<Function>, values: Vec<Expr>) -> Result<Expr> {
+        if values.len() != fun.args.len() {
+            return Err(EvalError::ArgumentNumber {
+                exp: fun.args.len(),
+                got: values.len(),
+            });
+        }
+        let frame = Env::child(&fun.env);
+        for (arg_name, value) in fun.args.iter().zip(values) {
+            frame.define_var(arg_name, value);
+        }
+        self.with_env(frame, |ctx| ctx.progn(&fun.body))
+    }
+
+    /// Call a function value, or the function a symbol names, on argument
+    /// values. Special forms cannot be called this way since their
+    /// arguments are not values.
+    fn call(&mut self, function: &Expr, values: Vec<Expr>) -> Result<Expr> {
+        let invalid = || EvalError::InvalidFunction {
+            function: function.to_string(),
+        };
+        match function {
+            Expr::Function(fun) => self.call_function(fun, values),
+            Atomic(Atom::Symbol(name)) => match self.lookup_function(name) {
+                Some(Callable::User(fun)) => self.call_function(&fun, values),
+                Some(Callable::Builtin(builtin)) => match builtin.kind {
+                    Kind::Function => {
+                        builtin.arity.check(values.len())?;
+                        (builtin.handler)(self, values)
+                    }
+                    Kind::SpecialForm => Err(invalid()),
+                },
+                None => Err(EvalError::VoidFunction {
                     name: name.to_string(),
                 }),
             },
-            Atomic(Atom::Op(_op)) => Err(EvalError::InvalidVarName),
+            _ => Err(invalid()),
+        }
+    }
+
+    fn apply_builtin(&mut self, builtin: Builtin, args: Vec<Expr>) -> Result<Expr> {
+        builtin.arity.check(args.len())?;
+        let args = match builtin.kind {
+            Kind::Function => args
+                .iter()
+                .map(|x| self.eval_ast(x))
+                .collect::<Result<Vec<Expr>>>()?,
+            Kind::SpecialForm => args,
+        };
+        (builtin.handler)(self, args)
+    }
+
+    pub fn eval_ast(&mut self, ast: &Expr) -> Result<Expr> {
+        match ast {
+            Atomic(Atom::Name(name)) => {
+                self.env
+                    .lookup_var(name)
+                    .ok_or_else(|| EvalError::VoidVariable {
+                        name: name.to_string(),
+                    })
+            }
             Atomic(atom) => Ok(Expr::Atomic(atom.clone())),
+            Expr::Function(_) => Ok(ast.clone()),
             Expr::List(sexp_list) => {
                 if sexp_list.is_empty() {
                     Ok(Atomic(Atom::Boolean(Bool::Nil)))
                 } else {
+                    if self.depth == MAX_DEPTH {
+                        return Err(EvalError::DepthExceeded { max: MAX_DEPTH });
+                    }
                     if self.debug {
                         print!("in:{:?}\r\n", sexp_list);
                     }
+                    self.depth += 1;
                     let res = self.apply(&sexp_list[0], sexp_list[1..].to_vec());
+                    self.depth -= 1;
                     if self.debug {
                         print!("out:{:?}\r\n", res);
                     }
                     res
                 }
             }
-            Qexpr(sexp_list) => Ok(Expr::List(sexp_list.to_vec())),
+            Qexpr(quoted) => Ok(datum(quoted)),
+            Expr::Quasiquote(template) => self.quasiquote(template, 1),
+            Expr::Unquote(_) | Expr::UnquoteSplicing(_) => Err(EvalError::UnquoteOutsideQuasiquote),
         }
     }
 
-    fn nth(&mut self, args: Vec<Expr>) -> Result<Expr> {
-        match args.as_slice() {
-            [Atomic(Atom::Number(Num::Int(idx))), Qexpr(vec)] => {
-                let answer = vec[*idx as usize].clone();
-                Ok(answer)
+    /// Expand a quasiquote template. `depth` counts the enclosing
+    /// quasiquotes, only unquotes at depth 1 are evaluated.
+    fn quasiquote(&mut self, template: &Expr, depth: usize) -> Result<Expr> {
+        match template {
+            Expr::Unquote(expr) if depth == 1 => self.eval_ast(expr),
+            Expr::Unquote(expr) => Ok(Expr::Unquote(Box::new(self.quasiquote(expr, depth - 1)?))),
+            Expr::UnquoteSplicing(_) if depth == 1 => Err(EvalError::SpliceOutsideList),
+            Expr::UnquoteSplicing(expr) => Ok(Expr::UnquoteSplicing(Box::new(
+                self.quasiquote(expr, depth - 1)?,
+            ))),
+            Expr::Quasiquote(expr) => Ok(Expr::Quasiquote(Box::new(
+                self.quasiquote(expr, depth + 1)?,
+            ))),
+            Expr::List(items) => Ok(Expr::List(self.quasiquote_items(items, depth)?)),
+            Qexpr(expr) => Ok(Qexpr(Box::new(self.quasiquote(expr, depth)?))),
+            Atomic(_) | Expr::Function(_) => Ok(datum(template)),
+        }
+    }
+
+    fn quasiquote_items(&mut self, items: &[Expr], depth: usize) -> Result<Vec<Expr>> {
+        let mut expanded = Vec::with_capacity(items.len());
+        for item in items {
+            match item {
+                Expr::UnquoteSplicing(expr) if depth == 1 => {
+                    let spliced = self.eval_ast(expr)?;
+                    let spliced = as_list(&spliced).ok_or(EvalError::WrongTypeArgumentList)?;
+                    expanded.extend_from_slice(spliced);
+                }
+                _ => expanded.push(self.quasiquote(item, depth)?),
             }
+        }
+        Ok(expanded)
+    }
+
+    pub(super) fn nth(&mut self, args: Vec<Expr>) -> Result<Expr> {
+        match (&args[0], as_list(&args[1])) {
+            (Atomic(Atom::Number(Num::Int(idx))), Some(vec)) => Ok(vec
+                .get(*idx as usize)
+                .cloned()
+                .unwrap_or(Atomic(Atom::Boolean(Bool::Nil)))),
             _ => Err(EvalError::InvalidArguments {
                 args: args
                     .iter()
@@ -116,15 +217,13 @@ impl Context {
         }
     }
 
-    fn map(&mut self, args: Vec<Expr>) -> Result<Expr> {
-        match args.as_slice() {
-            [func, Qexpr(vec)] => Ok(Expr::List(
+    /// `(mapcar f list)`: the list of the results of calling `f` on each
+    /// element of `list`
+    pub(super) fn mapcar(&mut self, args: Vec<Expr>) -> Result<Expr> {
+        match as_list(&args[1]) {
+            Some(vec) => Ok(Expr::List(
                 vec.iter()
-                    .map(|arg| match arg {
-                        Qexpr(arg_vec) => self.apply(func, arg_vec.to_vec()),
-                        Atomic(arg) => self.apply(func, vec![Atomic(arg.clone())]),
-                        Expr::List(arg) => self.apply(func, vec![Expr::List(arg.to_vec())]),
-                    })
+                    .map(|arg| self.call(&args[0], vec![arg.clone()]))
                     .collect::<Result<Vec<Expr>>>()?,
             )),
             _ => Err(EvalError::InvalidArguments {
@@ -137,71 +236,143 @@ impl Context {
         }
     }
 
-    fn defun(&mut self, args: Vec<Expr>) -> Result<Expr> {
-        if args.len() != 3 {
-            Err(EvalError::ArgumentNumber {
-                exp: 3,
-                got: args.len(),
-            })
-        } else {
-            match args.as_slice() {
-                [Atomic(Atom::Name(name)), Expr::List(fn_args), Expr::List(fn_body)] => {
-                    let function_args = fn_args
-                        .iter()
-                        .map(|x| {
-                            if let Atomic(Atom::Name(s)) = x {
-                                Ok(s.clone())
-                            } else {
-                                Err(EvalError::InvalidArguments {
-                                    args: fn_args
-                                        .iter()
-                                        .map(|x| format!("{}", x))
-                                        .collect::<Vec<String>>()
-                                        .join(" "),
-                                })
-                            }
-                        })
-                        .collect::<Result<Vec<String>>>()?;
-                    let function = Function {
-                        args: function_args,
-                        body: Expr::List(fn_body.to_vec()),
-                    };
-                    self.funcs.insert(name.to_string(), function);
-                    Ok(Atomic(Atom::Name(name.to_string())))
+    fn params(fn_args: &[Expr]) -> Result<Vec<String>> {
+        fn_args
+            .iter()
+            .map(|x| {
+                if let Atomic(Atom::Name(s)) = x {
+                    Ok(s.clone())
+                } else {
+                    Err(EvalError::InvalidArguments {
+                        args: fn_args
+                            .iter()
+                            .map(|x| format!("{}", x))
+                            .collect::<Vec<String>>()
+                            .join(" "),
+                    })
                 }
-                _ => Err(EvalError::InvalidSyntax),
+            })
+            .collect()
+    }
+
+    pub(super) fn defun(&mut self, args: Vec<Expr>) -> Result<Expr> {
+        match args.as_slice() {
+            [Atomic(Atom::Name(name)), Expr::List(fn_args), fn_body @ ..] => {
+                let function = Rc::new(Function {
+                    args: Self::params(fn_args)?,
+                    body: fn_body.to_vec(),
+                    env: Rc::clone(&self.env),
+                });
+                self.globals.define_function(name, function);
+                Ok(Atomic(Atom::Symbol(name.to_string())))
             }
+            _ => Err(EvalError::InvalidSyntax),
         }
     }
 
-    fn list(&mut self, args: Vec<Expr>) -> Result<Expr> {
-        Ok(Qexpr(
-            args.iter()
-                .map(|x| self.eval_ast(x))
-                .collect::<Result<Vec<Expr>>>()?,
-        ))
+    /// `(lambda (args...) body...)`: a function closing over the bindings in
+    /// scope
+    pub(super) fn lambda(&mut self, args: Vec<Expr>) -> Result<Expr> {
+        match args.as_slice() {
+            [Expr::List(fn_args), body @ ..] => Ok(Expr::Function(Rc::new(Function {
+                args: Self::params(fn_args)?,
+                body: body.to_vec(),
+                env: Rc::clone(&self.env),
+            }))),
+            _ => Err(EvalError::InvalidSyntax),
+        }
     }
 
-    fn eval_builtin(&mut self, args: Vec<Expr>) -> Result<Expr> {
-        if args.len() != 1 {
-            Err(EvalError::ArgumentNumber {
-                exp: 1,
-                got: args.len(),
-            })
-        } else {
-            if self.debug {
-                print!("eval-arg:{:?}\r\n", &args[0]);
+    /// `(funcall f args...)`: call the function value `f`, or the function
+    /// named by the symbol `f`
+    pub(super) fn funcall(&mut self, args: Vec<Expr>) -> Result<Expr> {
+        self.call(&args[0], args[1..].to_vec())
+    }
+
+    /// `(apply f args... list)`: like `funcall`, with the elements of `list`
+    /// as the last arguments
+    pub(super) fn apply_list(&mut self, mut args: Vec<Expr>) -> Result<Expr> {
+        let function = args.remove(0);
+        if let Some(last) = args.pop() {
+            let spread = as_list(&last).ok_or(EvalError::WrongTypeArgumentList)?;
+            args.extend_from_slice(spread);
+        }
+        self.call(&function, args)
+    }
+
+    /// `(flet ((name (args...) body...)...) forms...)`: evaluate `forms` with
+    /// local function definitions, which shadow global ones and builtins.
+    pub(super) fn flet(&mut self, args: Vec<Expr>) -> Result<Expr> {
+        let bindings = match &args[0] {
+            Expr::List(bindings) => bindings,
+            _ => return Err(EvalError::InvalidSyntax),
+        };
+        let frame = Env::child(&self.env);
+        for binding in bindings {
+            match binding {
+                Expr::List(def) => match def.as_slice() {
+                    [Atomic(Atom::Name(name)), Expr::List(fn_args), body @ ..] => {
+                        // The bodies see the enclosing bindings, not each other
+                        let function = Rc::new(Function {
+                            args: Self::params(fn_args)?,
+                            body: body.to_vec(),
+                            env: Rc::clone(&self.env),
+                        });
+                        frame.define_function(name, function);
+                    }
+                    _ => return Err(EvalError::InvalidSyntax),
+                },
+                _ => return Err(EvalError::InvalidSyntax),
             }
-            let interm = &self.eval_ast(&args[0])?;
-            Ok(self.eval_ast(interm)?)
         }
+        self.with_env(frame, |ctx| ctx.progn(&args[1..]))
     }
 
-    fn args_to_numbers(&mut self, args: Vec<Expr>) -> Result<Vec<Num>> {
+    /// Evaluate forms in order and return the value of the last one, nil if
+    /// there are none.
+    pub(super) fn progn(&mut self, forms: &[Expr]) -> Result<Expr> {
+        let mut res = Atomic(Atom::Boolean(Bool::Nil));
+        for form in forms {
+            res = self.eval_ast(form)?;
+        }
+        Ok(res)
+    }
+
+    /// `(builtin name args...)`: call the original builtin `name`, even if a
+    /// user definition shadows it.
+    pub(super) fn call_builtin(&mut self, args: Vec<Expr>) -> Result<Expr> {
+        match &args[0] {
+            Atomic(Atom::Name(name)) => match self.builtins.get(name) {
+                Some(builtin) => self.apply_builtin(*builtin, args[1..].to_vec()),
+                None => Err(EvalError::VoidFunction {
+                    name: name.to_string(),
+                }),
+            },
+            _ => Err(EvalError::InvalidSyntax),
+        }
+    }
+
+    pub(super) fn list(&mut self, args: Vec<Expr>) -> Result<Expr> {
+        Ok(Expr::List(args))
+    }
+
+    /// `(quote x)`: the long form of `'x`
+    pub(super) fn quote(&mut self, args: Vec<Expr>) -> Result<Expr> {
+        Ok(datum(&args[0]))
+    }
+
+    /// `(eval form)`: evaluate `form` in the global environment, it does not
+    /// see local bindings
+    pub(super) fn eval_builtin(&mut self, args: Vec<Expr>) -> Result<Expr> {
+        if self.debug {
+            print!("eval-arg:{:?}\r\n", &args[0]);
+        }
+        let globals = Rc::clone(&self.globals);
+        self.with_env(globals, |ctx| ctx.eval_ast(&code(&args[0])))
+    }
+
+    pub(super) fn args_to_numbers(&mut self, args: Vec<Expr>) -> Result<Vec<Num>> {
         args.into_iter()
-            .map(|x| self.eval_ast(&x))
-            .collect::<Result<Vec<Expr>>>()?
-            .into_iter()
             .map(|x| match x {
                 Atomic(Atom::Number(n)) => Ok(n),
                 _ => Err(EvalError::ShouldBeNum),
@@ -209,148 +380,146 @@ impl Context {
             .collect::<Result<Vec<Num>>>()
     }
 
-    fn add(&mut self, args: Vec<Expr>) -> Result<Expr> {
+    /// Left fold of `op` over numeric arguments. A single argument is
+    /// combined with `identity`, as in `(- x)` or `(/ x)`, and no argument
+    /// gives `identity`.
+    fn fold_numbers(
+        &mut self,
+        args: Vec<Expr>,
+        identity: Num,
+        op: fn(&Num, &Num) -> Result<Num>,
+    ) -> Result<Expr> {
         let num = self.args_to_numbers(args)?;
-        // Double type will spread and int will be cast to double
-        Ok(Atomic(Atom::Number(
-            if num.iter().any(|x| matches!(x, Num::Double(_))) {
-                Num::Double(
-                    num.iter()
-                        .map(|x| match x {
-                            Num::Int(i) => *i as f64,
-                            Num::Double(d) => *d,
-                        })
-                        .sum(),
-                )
-            } else {
-                Num::Int(
-                    num.iter()
-                        .map(|x| match x {
-                            Num::Int(i) => *i,
-                            _ => 0,
-                        })
-                        .try_fold(0, |acc: i64, x: i64| acc.checked_add(x))
-                        .ok_or(EvalError::IntOverflow)?,
-                )
-            },
-        )))
+        let res = match num.split_first() {
+            None => identity,
+            Some((x, [])) => op(&identity, x)?,
+            Some((first, rest)) => rest.iter().try_fold(first.clone(), |acc, x| op(&acc, x))?,
+        };
+        Ok(Atomic(Atom::Number(res)))
+    }
+
+    pub(super) fn add(&mut self, args: Vec<Expr>) -> Result<Expr> {
+        self.fold_numbers(args, Num::Int(0), numeric::add)
     }
 
-    fn mul(&mut self, args: Vec<Expr>) -> Result<Expr> {
+    pub(super) fn mul(&mut self, args: Vec<Expr>) -> Result<Expr> {
+        self.fold_numbers(args, Num::Int(1), numeric::mul)
+    }
+
+    /// `(- x)` negates `x`, `(- x y...)` subtracts each `y` from `x`.
+    pub(super) fn sub(&mut self, args: Vec<Expr>) -> Result<Expr> {
+        self.fold_numbers(args, Num::Int(0), numeric::sub)
+    }
+
+    /// `(/ x)` is the reciprocal of `x`, `(/ x y...)` divides `x` by each `y`.
+    pub(super) fn div(&mut self, args: Vec<Expr>) -> Result<Expr> {
+        self.fold_numbers(args, Num::Int(1), numeric::div)
+    }
+
+    /// `(numerator x)`: numerator of a rational in lowest terms
+    pub(super) fn numerator(&mut self, args: Vec<Expr>) -> Result<Expr> {
         let num = self.args_to_numbers(args)?;
-        // Double type will spread and int will be cast to double
-        Ok(Atomic(Atom::Number(
-            if num.iter().any(|x| matches!(x, Num::Double(_))) {
-                Num::Double(
-                    num.iter()
-                        .map(|x| match x {
-                            Num::Int(i) => *i as f64,
-                            Num::Double(d) => *d,
-                        })
-                        .fold(1., |acc, x| acc * x),
-                )
-            } else {
-                Num::Int(
-                    num.iter()
-                        .map(|x| match x {
-                            Num::Int(i) => *i,
-                            _ => 0,
-                        })
-                        .try_fold(1, |acc: i64, x: i64| acc.checked_mul(x))
-                        .ok_or(EvalError::IntOverflow)?,
-                )
-            },
-        )))
+        let res = numeric::numerator(&num[0]).ok_or(EvalError::ShouldBeRational)?;
+        Ok(Atomic(Atom::Number(res)))
     }
 
-    fn sub(&mut self, args: Vec<Expr>) -> Result<Expr> {
-        if args.len() != 2 {
-            return Err(EvalError::ArgumentNumber {
-                exp: 2,
-                got: args.len(),
-            });
-        }
+    /// `(denominator x)`: denominator of a rational in lowest terms, 1 for
+    /// an integer
+    pub(super) fn denominator(&mut self, args: Vec<Expr>) -> Result<Expr> {
         let num = self.args_to_numbers(args)?;
-        Ok(Atomic(Atom::Number(
-            if num.iter().any(|x| matches!(x, Num::Double(_))) {
-                Num::Double({
-                    let neg: Vec<f64> = num
-                        .iter()
-                        .map(|x| match x {
-                            Num::Int(i) => *i as f64,
-                            Num::Double(d) => *d,
-                        })
-                        .collect();
-                    neg[0] - neg[1]
-                })
-            } else {
-                Num::Int({
-                    let neg: Vec<i64> = num
-                        .iter()
-                        .map(|x| match x {
-                            Num::Int(i) => *i,
-                            _ => 0,
-                        })
-                        .collect();
-                    neg[0].checked_sub(neg[1]).ok_or(EvalError::IntOverflow)?
-                })
-            },
-        )))
+        let res = numeric::denominator(&num[0]).ok_or(EvalError::ShouldBeRational)?;
+        Ok(Atomic(Atom::Number(res)))
     }
 
-    fn div(&mut self, args: Vec<Expr>) -> Result<Expr> {
-        if args.len() != 2 {
-            return Err(EvalError::ArgumentNumber {
-                exp: 2,
-                got: args.len(),
-            });
-        }
+    /// Apply `f` to numeric arguments.
+    pub(super) fn with_numbers(
+        &mut self,
+        args: Vec<Expr>,
+        f: impl FnOnce(&[Num]) -> Result<Num>,
+    ) -> Result<Expr> {
         let num = self.args_to_numbers(args)?;
-        Ok(Atomic(Atom::Number(
-            if num.iter().any(|x| matches!(x, Num::Double(_))) {
-                Num::Double({
-                    let neg: Vec<f64> = num
-                        .iter()
-                        .map(|x| match x {
-                            Num::Int(i) => *i as f64,
-                            Num::Double(d) => *d,
-                        })
-                        .collect();
-                    neg[0] / neg[1]
-                })
-            } else {
-                Num::Int({
-                    let neg: Vec<i64> = num
-                        .iter()
-                        .map(|x| match x {
-                            Num::Int(i) => *i,
-                            _ => 0,
-                        })
-                        .collect();
-                    neg[0].checked_div(neg[1]).ok_or(EvalError::DivBy0)?
-                })
-            },
-        )))
+        Ok(Atomic(Atom::Number(f(&num)?)))
     }
 
-    /// Return the first element of a list or nil if empty
-    fn car(&mut self, args: Vec<Expr>) -> Result<Expr> {
-        if args.len() != 1 {
-            Err(EvalError::ArgumentNumber {
-                exp: 1,
-                got: args.len(),
-            })
-        } else {
-            match self.eval_ast(&args[0])? {
-                Expr::List(c) => {
-                    if c.is_empty() {
-                        Ok(Expr::Atomic(Atom::Boolean(Bool::Nil)))
-                    } else {
-                        Ok(c[0].clone())
-                    }
-                }
-                Qexpr(_) | Atomic(_) => Err(EvalError::WrongTypeArgumentList),
+    /// Apply `f` to a single number argument.
+    fn map_number(&mut self, args: Vec<Expr>, f: fn(&Num) -> Num) -> Result<Expr> {
+        self.with_numbers(args, |num| Ok(f(&num[0])))
+    }
+
+    pub(super) fn real_part(&mut self, args: Vec<Expr>) -> Result<Expr> {
+        self.map_number(args, numeric::real_part)
+    }
+
+    pub(super) fn imag_part(&mut self, args: Vec<Expr>) -> Result<Expr> {
+        self.map_number(args, numeric::imag_part)
+    }
+
+    pub(super) fn magnitude(&mut self, args: Vec<Expr>) -> Result<Expr> {
+        self.map_number(args, numeric::magnitude)
+    }
+
+    pub(super) fn angle(&mut self, args: Vec<Expr>) -> Result<Expr> {
+        self.map_number(args, numeric::angle)
+    }
+
+    pub(super) fn conjugate(&mut self, args: Vec<Expr>) -> Result<Expr> {
+        self.map_number(args, numeric::conjugate)
+    }
+
+    /// `(decimal x)`: convert a real number to a decimal
+    pub(super) fn decimal(&mut self, args: Vec<Expr>) -> Result<Expr> {
+        let num = self.args_to_numbers(args)?;
+        Ok(Atomic(Atom::Number(numeric::to_decimal(&num[0])?)))
+    }
+
+    /// `(double x)`: convert a real number to a float
+    pub(super) fn double(&mut self, args: Vec<Expr>) -> Result<Expr> {
+        let num = self.args_to_numbers(args)?;
+        Ok(Atomic(Atom::Number(numeric::to_double(&num[0])?)))
+    }
+
+    /// `(round-decimal x places [mode])`: round an exact number to a decimal
+    /// with `places` digits after the point. `mode` is `'half-even` (the
+    /// default), `'half-up` or `'truncate`.
+    pub(super) fn round_decimal(&mut self, args: Vec<Expr>) -> Result<Expr> {
+        let invalid = || EvalError::InvalidArguments {
+            args: args
+                .iter()
+                .map(|x| format!("{}", x))
+                .collect::<Vec<String>>()
+                .join(" "),
+        };
+        let places = match &args[1] {
+            Atomic(Atom::Number(Num::Int(places))) => {
+                u32::try_from(*places).map_err(|_| invalid())?
             }
+            _ => return Err(invalid()),
+        };
+        let mode = match args.get(2) {
+            None => numeric::rounding_mode("half-even"),
+            Some(Atomic(Atom::Symbol(name))) => numeric::rounding_mode(name),
+            Some(_) => None,
+        }
+        .ok_or_else(invalid)?;
+        match &args[0] {
+            Atomic(Atom::Number(x)) => Ok(Atomic(Atom::Number(numeric::round_decimal(
+                x, places, mode,
+            )?))),
+            _ => Err(EvalError::ShouldBeNum),
+        }
+    }
+
+    /// `(null x)`: t if `x` is false, i.e. nil or the empty list
+    pub(super) fn null(&mut self, args: Vec<Expr>) -> Result<Expr> {
+        Ok(boolean(!truthy(&args[0])))
+    }
+
+    /// Return the first element of a list or nil if empty
+    pub(super) fn car(&mut self, args: Vec<Expr>) -> Result<Expr> {
+        match as_list(&args[0]) {
+            Some([]) => Ok(Expr::Atomic(Atom::Boolean(Bool::Nil))),
+            Some(c) => Ok(c[0].clone()),
+            None => Err(EvalError::WrongTypeArgumentList),
         }
     }
 }
@@ -358,8 +527,15 @@ impl Context {
 #[cfg(test)]
 mod tests {
     use super::Context;
+    use crate::evaluator::builtins::Arity;
     use crate::evaluator::context::Function;
-    use crate::parser::{Atom, Expr, Expr::Atomic, Num, Ops};
+    use crate::evaluator::{eval_str, show, EvalError};
+    use crate::parser::{Atom, Expr, Expr::Atomic, Num};
+    use std::rc::Rc;
+
+    fn int(i: i64) -> Expr {
+        Atomic(Atom::Number(Num::Int(i)))
+    }
 
     #[test]
     fn should_eval_atomic() {
@@ -374,12 +550,12 @@ mod tests {
         let mut ctx = Context::default();
         let ast = Expr::List(
             [
-                Atomic(Atom::Op(Ops::Add)),
+                Atomic(Atom::Name("+".to_string())),
                 Atomic(Atom::Number(Num::Int(3))),
                 Atomic(Atom::Number(Num::Int(3))),
                 Expr::List(
                     [
-                        Atomic(Atom::Op(Ops::Add)),
+                        Atomic(Atom::Name("+".to_string())),
                         Atomic(Atom::Number(Num::Int(5))),
                         Atomic(Atom::Number(Num::Int(9))),
                     ]
@@ -398,12 +574,12 @@ mod tests {
         ctx.add_var("x", Atomic(Atom::Number(Num::Int(12))));
         let ast = Expr::List(
             [
-                Atomic(Atom::Op(Ops::Add)),
+                Atomic(Atom::Name("+".to_string())),
                 Atomic(Atom::Number(Num::Int(3))),
                 Atomic(Atom::Name("x".to_string())),
                 Expr::List(
                     [
-                        Atomic(Atom::Op(Ops::Mul)),
+                        Atomic(Atom::Name("*".to_string())),
                         Atomic(Atom::Number(Num::Int(9))),
                         Atomic(Atom::Name("x".to_string())),
                     ]
@@ -419,26 +595,28 @@ mod tests {
     #[test]
     fn should_eval_function() {
         let mut ctx = Context::default();
-        ctx.funcs.insert(
-            "square".to_string(),
-            Function {
+        let env = ctx.globals.clone();
+        ctx.globals.define_function(
+            "square",
+            Rc::new(Function {
                 args: vec!["x".to_string()],
-                body: Expr::List(
+                body: vec![Expr::List(
                     [
-                        Atomic(Atom::Op(Ops::Mul)),
+                        Atomic(Atom::Name("*".to_string())),
                         Atomic(Atom::Name("x".to_string())),
                         Atomic(Atom::Name("x".to_string())),
                     ]
                     .to_vec(),
-                ),
-            },
+                )],
+                env,
+            }),
         );
         let ast = Expr::List(
             [
                 Atomic(Atom::Name("square".to_string())),
                 Expr::List(
                     [
-                        Atomic(Atom::Op(Ops::Add)),
+                        Atomic(Atom::Name("+".to_string())),
                         Atomic(Atom::Number(Num::Int(5))),
                         Atomic(Atom::Number(Num::Int(9))),
                     ]
@@ -456,12 +634,12 @@ mod tests {
         let mut ctx = Context::default();
         let ast = Expr::List(
             [
-                Atomic(Atom::Op(Ops::Defun)),
+                Atomic(Atom::Name("defun".to_string())),
                 Atomic(Atom::Name("square".to_string())),
                 Expr::List([Atomic(Atom::Name("x".to_string()))].to_vec()),
                 Expr::List(
                     [
-                        Atomic(Atom::Op(Ops::Mul)),
+                        Atomic(Atom::Name("*".to_string())),
                         Atomic(Atom::Name("x".to_string())),
                         Atomic(Atom::Name("x".to_string())),
                     ]
@@ -473,7 +651,7 @@ mod tests {
         let result = ctx.eval_ast(&ast);
         assert_eq!(
             result.unwrap(),
-            Expr::Atomic(Atom::Name("square".to_string()))
+            Expr::Atomic(Atom::Symbol("square".to_string()))
         );
     }
 
@@ -482,12 +660,12 @@ mod tests {
         let mut ctx = Context::default();
         let ast = Expr::List(
             [
-                Atomic(Atom::Op(Ops::Defun)),
+                Atomic(Atom::Name("defun".to_string())),
                 Atomic(Atom::Name("first".to_string())),
                 Expr::List([Atomic(Atom::Name("x".to_string()))].to_vec()),
                 Expr::List(
                     [
-                        Atomic(Atom::Op(Ops::Car)),
+                        Atomic(Atom::Name("car".to_string())),
                         Atomic(Atom::Name("x".to_string())),
                     ]
                     .to_vec(),
@@ -496,18 +674,18 @@ mod tests {
             .to_vec(),
         );
         let result = ctx.eval_ast(&ast);
-        assert_eq!(result.unwrap(), Atomic(Atom::Name("first".to_string())));
+        assert_eq!(result.unwrap(), Atomic(Atom::Symbol("first".to_string())));
         let ast = Expr::List(
             [
                 Atomic(Atom::Name("first".to_string())),
-                Expr::Qexpr(
+                Expr::Qexpr(Box::new(Expr::List(
                     [
                         Atomic(Atom::Number(Num::Int(5))),
                         Atomic(Atom::Number(Num::Int(6))),
                         Atomic(Atom::Number(Num::Int(12))),
                     ]
                     .to_vec(),
-                ),
+                ))),
             ]
             .to_vec(),
         );
@@ -521,20 +699,20 @@ mod tests {
         // (car '((car '(3.231 6 9)) 3 8.1))
         let ast = Expr::List(
             [
-                Atomic(Atom::Op(Ops::Car)),
-                Expr::Qexpr(
+                Atomic(Atom::Name("car".to_string())),
+                Expr::Qexpr(Box::new(Expr::List(
                     [
                         Expr::List(
                             [
-                                Atomic(Atom::Op(Ops::Car)),
-                                Expr::Qexpr(
+                                Atomic(Atom::Name("car".to_string())),
+                                Expr::Qexpr(Box::new(Expr::List(
                                     [
                                         Atomic(Atom::Number(Num::Double(3.231))),
                                         Atomic(Atom::Number(Num::Int(6))),
                                         Atomic(Atom::Number(Num::Int(9))),
                                     ]
                                     .to_vec(),
-                                ),
+                                ))),
                             ]
                             .to_vec(),
                         ),
@@ -542,7 +720,7 @@ mod tests {
                         Atomic(Atom::Number(Num::Double(8.1))),
                     ]
                     .to_vec(),
-                ),
+                ))),
             ]
             .to_vec(),
         );
@@ -551,23 +729,488 @@ mod tests {
             result,
             Expr::List(
                 [
-                    Atomic(Atom::Op(Ops::Car)),
-                    Expr::Qexpr(
+                    Atomic(Atom::Symbol("car".to_string())),
+                    Expr::Qexpr(Box::new(Expr::List(
                         [
                             Atomic(Atom::Number(Num::Double(3.231))),
                             Atomic(Atom::Number(Num::Int(6))),
                             Atomic(Atom::Number(Num::Int(9))),
                         ]
                         .to_vec(),
-                    ),
+                    ))),
                 ]
                 .to_vec(),
             ),
         );
 
         // (eval (car '((car '(3.231 6 9)) 3 8.1)))
-        let ast = Expr::List([Atomic(Atom::Op(Ops::Eval)), result].to_vec());
+        let ast = Expr::List([Atomic(Atom::Name("eval".to_string())), result].to_vec());
         let result = ctx.eval_ast(&ast).unwrap();
         assert_eq!(result, Atomic(Atom::Number(Num::Double(3.231))));
     }
+
+    #[test]
+    fn should_check_builtin_arity() {
+        let mut ctx = Context::default();
+        let ast = Expr::List(
+            [
+                Atomic(Atom::Name("car".to_string())),
+                Atomic(Atom::Number(Num::Int(1))),
+                Atomic(Atom::Number(Num::Int(2))),
+            ]
+            .to_vec(),
+        );
+        assert!(matches!(
+            ctx.eval_ast(&ast),
+            Err(EvalError::ArgumentNumber { exp: 1, got: 2 })
+        ));
+    }
+
+    #[test]
+    fn should_call_registered_builtin() {
+        let mut ctx = Context::default();
+        ctx.builtins
+            .function("second", Arity::Exact(2), |_, args| Ok(args[1].clone()));
+        let ast = Expr::List(
+            [
+                Atomic(Atom::Name("second".to_string())),
+                Atomic(Atom::Number(Num::Int(1))),
+                Expr::List(
+                    [
+                        Atomic(Atom::Name("+".to_string())),
+                        Atomic(Atom::Number(Num::Int(2))),
+                        Atomic(Atom::Number(Num::Int(3))),
+                    ]
+                    .to_vec(),
+                ),
+            ]
+            .to_vec(),
+        );
+        let result = ctx.eval_ast(&ast);
+        assert_eq!(result.unwrap(), Atomic(Atom::Number(Num::Int(5))));
+    }
+
+    #[test]
+    fn should_shadow_builtins_globally() {
+        let mut ctx = Context::default();
+        eval_str(&mut ctx, "(defun list (x) (* x 2))").unwrap();
+        assert_eq!(eval_str(&mut ctx, "(list 4)").unwrap(), int(8));
+        assert_eq!(
+            eval_str(&mut ctx, "(builtin list 4 5)").unwrap(),
+            Expr::List([int(4), int(5)].to_vec())
+        );
+        eval_str(&mut ctx, "(defun first (list) (car list))").unwrap();
+        assert_eq!(eval_str(&mut ctx, "(first '(3 4))").unwrap(), int(3));
+    }
+
+    #[test]
+    fn should_shadow_builtins_locally() {
+        let mut ctx = Context::default();
+        assert_eq!(
+            eval_str(&mut ctx, "(flet ((car (x) (nth 1 x))) (car '(1 2 3)))").unwrap(),
+            int(2)
+        );
+        assert_eq!(
+            eval_str(
+                &mut ctx,
+                "(flet ((car (x) (nth 1 x))) (builtin car '(1 2 3)))"
+            )
+            .unwrap(),
+            int(1)
+        );
+        assert_eq!(eval_str(&mut ctx, "(car '(1 2 3))").unwrap(), int(1));
+        assert!(matches!(
+            eval_str(&mut ctx, "(builtin square 2)"),
+            Err(EvalError::VoidFunction { .. })
+        ));
+    }
+
+    #[test]
+    fn should_read_back_booleans() {
+        let mut ctx = Context::default();
+        let nil = eval_str(&mut ctx, "()").unwrap();
+        assert_eq!(nil.to_string(), "nil");
+        assert_eq!(eval_str(&mut ctx, "nil").unwrap(), nil);
+        assert_eq!(show(&mut ctx, "(null nil)"), "t");
+        // nil and () are the empty list
+        assert_eq!(eval_str(&mut ctx, "(car nil)").unwrap(), nil);
+        assert_eq!(eval_str(&mut ctx, "(car ())").unwrap(), nil);
+        assert_eq!(eval_str(&mut ctx, "(car (car '(())))").unwrap(), nil);
+        assert_eq!(eval_str(&mut ctx, "(nth 0 nil)").unwrap(), nil);
+        assert_eq!(show(&mut ctx, "(mapcar 'car nil)"), "()");
+        assert_eq!(show(&mut ctx, "(apply 'list 1 nil)"), "(1)");
+        assert_eq!(show(&mut ctx, "`(1 ,@nil 2)"), "(1 2)");
+        assert_eq!(show(&mut ctx, "(null '())"), "t");
+        assert_eq!(eval_str(&mut ctx, "(null (null nil))").unwrap(), nil);
+        assert_eq!(eval_str(&mut ctx, "(null 0)").unwrap(), nil);
+        assert_eq!(show(&mut ctx, "(car '(t 2))"), "t");
+    }
+
+    #[test]
+    fn should_expand_quasiquotes() {
+        let mut ctx = Context::default();
+        eval_str(&mut ctx, "(defun f (x rest) `(a ,x ,@rest (b ,(+ x 1))))").unwrap();
+        let res = eval_str(&mut ctx, "(f 1 '(2 3))").unwrap();
+        assert_eq!(res.to_string(), "(a 1 2 3 (b 2))");
+        let res = eval_str(&mut ctx, "(f 1 ())").unwrap();
+        assert_eq!(res.to_string(), "(a 1 (b 2))");
+        let res = eval_str(&mut ctx, "`(1 `(2 ,(3 ,(+ 1 3))))").unwrap();
+        assert_eq!(res.to_string(), "(1 `(2 ,(3 4)))");
+        assert_eq!(eval_str(&mut ctx, "`,(+ 1 2)").unwrap(), int(3));
+        assert!(matches!(
+            eval_str(&mut ctx, ",x"),
+            Err(EvalError::UnquoteOutsideQuasiquote)
+        ));
+        assert!(matches!(
+            eval_str(&mut ctx, "`,@(list 1)"),
+            Err(EvalError::SpliceOutsideList)
+        ));
+    }
+
+    #[test]
+    fn should_quote_any_datum() {
+        let mut ctx = Context::default();
+        let sym = Atomic(Atom::Symbol("foo".to_string()));
+        assert_eq!(eval_str(&mut ctx, "'foo").unwrap(), sym);
+        assert_eq!(eval_str(&mut ctx, "(quote foo)").unwrap(), sym);
+        assert_eq!(eval_str(&mut ctx, "'42").unwrap(), int(42));
+        assert_eq!(
+            eval_str(&mut ctx, "(quote (foo 1))").unwrap(),
+            eval_str(&mut ctx, "'(foo 1)").unwrap()
+        );
+        assert_eq!(show(&mut ctx, "''foo"), "'foo");
+        assert_eq!(eval_str(&mut ctx, "(car '(foo))").unwrap(), sym);
+        assert!(matches!(
+            eval_str(&mut ctx, "foo"),
+            Err(EvalError::VoidVariable { .. })
+        ));
+        assert_eq!(eval_str(&mut ctx, "(eval '(car '(1 2)))").unwrap(), int(1));
+        eval_str(&mut ctx, "(defun f (x) (eval (list '+ x 1)))").unwrap();
+        assert_eq!(eval_str(&mut ctx, "(f 2)").unwrap(), int(3));
+    }
+
+    #[test]
+    fn should_compute_big_integers() {
+        let mut ctx = Context::default();
+        let fact = "(* 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25)";
+        let res = eval_str(&mut ctx, fact).unwrap();
+        assert_eq!(res.to_string(), "15511210043330985984000000");
+        assert_eq!(
+            eval_str(&mut ctx, &format!("(/ {} {})", fact, fact)).unwrap(),
+            int(1)
+        );
+        assert_eq!(
+            eval_str(&mut ctx, "(- 9223372036854775808 1)").unwrap(),
+            int(i64::MAX)
+        );
+        assert_eq!(
+            show(&mut ctx, "(+ 9223372036854775807 1)"),
+            "9223372036854775808"
+        );
+        assert!(matches!(
+            eval_str(&mut ctx, "(/ 100000000000000000000 0)"),
+            Err(EvalError::DivBy0)
+        ));
+    }
+
+    #[test]
+    fn should_compute_rationals() {
+        let mut ctx = Context::default();
+        assert_eq!(show(&mut ctx, "(/ 1 3)"), "1/3");
+        assert_eq!(eval_str(&mut ctx, "(/ 6 3)").unwrap(), int(2));
+        assert_eq!(eval_str(&mut ctx, "(+ 1/3 2/3)").unwrap(), int(1));
+        assert_eq!(show(&mut ctx, "(* 1/3 -3/4)"), "-1/4");
+        assert_eq!(
+            eval_str(&mut ctx, "(+ 1/2 0.25)").unwrap(),
+            Atomic(Atom::Number(Num::Double(0.75)))
+        );
+        assert_eq!(eval_str(&mut ctx, "(numerator 6/4)").unwrap(), int(3));
+        assert_eq!(eval_str(&mut ctx, "(denominator (/ 6 4))").unwrap(), int(2));
+        assert_eq!(eval_str(&mut ctx, "(denominator 7)").unwrap(), int(1));
+        assert!(matches!(
+            eval_str(&mut ctx, "(numerator 0.5)"),
+            Err(EvalError::ShouldBeRational)
+        ));
+    }
+
+    #[test]
+    fn should_compute_complex_numbers() {
+        let mut ctx = Context::default();
+        assert_eq!(show(&mut ctx, "(+ 3+4i 1)"), "4.0+4.0i");
+        assert_eq!(show(&mut ctx, "(* +1i +1i)"), "-1.0+0.0i");
+        assert_eq!(show(&mut ctx, "(- 1/2 2.5-i)"), "-2.0+1.0i");
+        assert_eq!(show(&mut ctx, "(/ 3+4i 2)"), "1.5+2.0i");
+        assert_eq!(show(&mut ctx, "(real-part 3-4i)"), "3.0");
+        assert_eq!(show(&mut ctx, "(imag-part 3-4i)"), "-4.0");
+        assert_eq!(show(&mut ctx, "(imag-part 3)"), "0");
+        assert_eq!(show(&mut ctx, "(magnitude 3-4i)"), "5.0");
+        assert_eq!(show(&mut ctx, "(magnitude -3)"), "3");
+        assert_eq!(
+            show(&mut ctx, "(angle -1)"),
+            std::f64::consts::PI.to_string()
+        );
+        assert_eq!(show(&mut ctx, "(conjugate 3-4i)"), "3.0+4.0i");
+        assert_eq!(show(&mut ctx, "(conjugate 3)"), "3");
+    }
+
+    #[test]
+    fn should_compute_decimals() {
+        let mut ctx = Context::default();
+        assert_eq!(show(&mut ctx, "(+ 0.1m 0.2m)"), "0.3m");
+        assert_eq!(show(&mut ctx, "(* 12.50m 3)"), "37.50m");
+        assert_eq!(show(&mut ctx, "(- 10m 0.01m)"), "9.99m");
+        assert_eq!(show(&mut ctx, "(/ 10.00m 4)"), "2.50m");
+        assert_eq!(show(&mut ctx, "(+ 0.5m 1/3)"), "5/6");
+        assert_eq!(show(&mut ctx, "(round-decimal 2.345m 2)"), "2.34m");
+        assert_eq!(show(&mut ctx, "(round-decimal 2.345m 2 'half-up)"), "2.35m");
+        assert_eq!(
+            show(&mut ctx, "(round-decimal -2.349m 2 'truncate)"),
+            "-2.34m"
+        );
+        assert_eq!(show(&mut ctx, "(round-decimal (/ 2 3) 4)"), "0.6667m");
+        assert_eq!(show(&mut ctx, "(decimal 0.1)"), "0.1m");
+        assert_eq!(show(&mut ctx, "(decimal 1/4)"), "0.25m");
+        assert_eq!(show(&mut ctx, "(double 12.50m)"), "12.5");
+        assert!(matches!(
+            eval_str(&mut ctx, "(+ 0.1m 0.2)"),
+            Err(EvalError::InexactDecimal)
+        ));
+        assert!(matches!(
+            eval_str(&mut ctx, "(/ 1m 0m)"),
+            Err(EvalError::DivBy0)
+        ));
+        assert!(matches!(
+            eval_str(&mut ctx, "(round-decimal 1.5m 0 'sideways)"),
+            Err(EvalError::InvalidArguments { .. })
+        ));
+        assert!(matches!(
+            eval_str(&mut ctx, "(round-decimal 1.5m 0 'half-up 1)"),
+            Err(EvalError::TooManyArguments { max: 3, got: 4 })
+        ));
+        assert!(matches!(
+            eval_str(&mut ctx, "(decimal 1e100)"),
+            Err(EvalError::DecimalOverflow)
+        ));
+        for x in ["nan", "inf", "-inf"] {
+            assert!(matches!(
+                eval_str(&mut ctx, &format!("(decimal {})", x)),
+                Err(EvalError::Domain { name, arg }) if name == "decimal" && arg == x
+            ));
+        }
+    }
+
+    #[test]
+    fn should_fold_sub_and_div() {
+        let mut ctx = Context::default();
+        assert_eq!(show(&mut ctx, "(- 5)"), "-5");
+        assert_eq!(show(&mut ctx, "(- 10 1 2 3)"), "4");
+        assert_eq!(
+            show(&mut ctx, "(- -9223372036854775808)"),
+            "9223372036854775808"
+        );
+        assert_eq!(show(&mut ctx, "(- 2.5m)"), "-2.5m");
+        assert_eq!(show(&mut ctx, "(- 1+2i)"), "-1.0-2.0i");
+        assert_eq!(show(&mut ctx, "(/ 4)"), "1/4");
+        assert_eq!(show(&mut ctx, "(/ 0.5)"), "2.0");
+        assert_eq!(show(&mut ctx, "(/ 60 2 3 5)"), "2");
+        assert_eq!(show(&mut ctx, "(/ 1 2 3)"), "1/6");
+        assert_eq!(show(&mut ctx, "(+ 7)"), "7");
+        assert_eq!(show(&mut ctx, "(*)"), "1");
+        assert!(matches!(
+            eval_str(&mut ctx, "(-)"),
+            Err(EvalError::TooFewArguments { min: 1, got: 0 })
+        ));
+        assert!(matches!(
+            eval_str(&mut ctx, "(/ 0)"),
+            Err(EvalError::DivBy0)
+        ));
+        assert!(matches!(
+            eval_str(&mut ctx, "(/ 1 2 0)"),
+            Err(EvalError::DivBy0)
+        ));
+    }
+
+    #[test]
+    fn should_scope_lexically() {
+        let mut ctx = Context::default();
+        ctx.add_var("x", int(10));
+        eval_str(&mut ctx, "(defun double (x) (* x 2))").unwrap();
+        assert_eq!(eval_str(&mut ctx, "(double 3)").unwrap(), int(6));
+        assert_eq!(eval_str(&mut ctx, "x").unwrap(), int(10));
+        // The argument sees the caller's bindings, the body does not
+        eval_str(&mut ctx, "(defun quadruple (x) (double (double x)))").unwrap();
+        assert_eq!(eval_str(&mut ctx, "(quadruple 3)").unwrap(), int(12));
+        eval_str(&mut ctx, "(defun get-y () y)").unwrap();
+        eval_str(&mut ctx, "(defun call-with-y (y) (get-y))").unwrap();
+        assert!(matches!(
+            eval_str(&mut ctx, "(call-with-y 1)"),
+            Err(EvalError::VoidVariable { .. })
+        ));
+        eval_str(&mut ctx, "(defun first (l) (car l))").unwrap();
+        assert_eq!(
+            show(
+                &mut ctx,
+                "(flet ((car (l) 42)) (list (car '(1)) (first '(1))))"
+            ),
+            "(42 1)"
+        );
+    }
+
+    #[test]
+    fn should_keep_bindings_per_activation() {
+        let mut ctx = Context::default();
+        eval_str(
+            &mut ctx,
+            "(defun nest (n) (if (nth n '(t)) () (list n (nest (- n 1)) n)))",
+        )
+        .unwrap();
+        assert_eq!(show(&mut ctx, "(nest 3)"), "(3 (2 (1 nil 1) 2) 3)");
+    }
+
+    /// A context with a `(tick)` function counting its calls in `ticks`.
+    fn counting_context() -> Context {
+        let mut ctx = Context::default();
+        ctx.add_var("ticks", int(0));
+        ctx.builtins.function("tick", Arity::Exact(0), |ctx, _| {
+            let ticks = match ctx.globals.lookup_var("ticks") {
+                Some(Atomic(Atom::Number(Num::Int(n)))) => n + 1,
+                _ => unreachable!(),
+            };
+            ctx.globals.define_var("ticks", int(ticks));
+            Ok(int(ticks))
+        });
+        ctx
+    }
+
+    #[test]
+    fn should_evaluate_arguments_once() {
+        let mut ctx = counting_context();
+        eval_str(&mut ctx, "(defun twice (x) (list x x))").unwrap();
+        assert_eq!(show(&mut ctx, "(twice (tick))"), "(1 1)");
+        assert_eq!(eval_str(&mut ctx, "ticks").unwrap(), int(1));
+        // Even when the parameter is never used
+        eval_str(&mut ctx, "(defun ignore (x) 0)").unwrap();
+        assert_eq!(eval_str(&mut ctx, "(ignore (tick))").unwrap(), int(0));
+        assert_eq!(eval_str(&mut ctx, "ticks").unwrap(), int(2));
+        assert!(matches!(
+            eval_str(&mut ctx, "(ignore undefined)"),
+            Err(EvalError::VoidVariable { .. })
+        ));
+        // Not at all when the call has the wrong number of arguments
+        assert!(matches!(
+            eval_str(&mut ctx, "(twice (tick) (tick))"),
+            Err(EvalError::ArgumentNumber { exp: 1, got: 2 })
+        ));
+        assert_eq!(eval_str(&mut ctx, "ticks").unwrap(), int(2));
+    }
+
+    #[test]
+    fn should_evaluate_arguments_left_to_right() {
+        let mut ctx = counting_context();
+        eval_str(&mut ctx, "(defun pair (a b) (list a b))").unwrap();
+        eval_str(&mut ctx, "(defun swap (a b) (pair b a))").unwrap();
+        assert_eq!(show(&mut ctx, "(swap (tick) (tick))"), "(2 1)");
+        assert_eq!(eval_str(&mut ctx, "ticks").unwrap(), int(2));
+    }
+
+    #[test]
+    fn should_bind_values() {
+        let mut ctx = Context::default();
+        eval_str(&mut ctx, "(defun id (x) x)").unwrap();
+        // A symbol is bound as is, it is not looked up when the parameter is
+        assert_eq!(show(&mut ctx, "(id 'y)"), "y");
+        assert_eq!(show(&mut ctx, "(id (id '(a b)))"), "(a b)");
+        // Names in an argument refer to the caller's parameters, even when
+        // the callee uses the same names
+        eval_str(&mut ctx, "(defun pair (x y) (list x y))").unwrap();
+        eval_str(&mut ctx, "(defun flip (x y) (pair y x))").unwrap();
+        assert_eq!(show(&mut ctx, "(flip 1 2)"), "(2 1)");
+    }
+
+    #[test]
+    fn should_call_lambdas() {
+        let mut ctx = Context::default();
+        assert_eq!(show(&mut ctx, "((lambda (x) (* x x)) 4)"), "16");
+        assert_eq!(
+            show(&mut ctx, "(lambda (x y) (+ x y))"),
+            "#<lambda (x y) (+ x y)>"
+        );
+        assert_eq!(show(&mut ctx, "(funcall (lambda () 'none))"), "none");
+        // Bodies are evaluated in order, like a progn
+        assert_eq!(
+            show(&mut ctx, "(lambda (x) (list x) x)"),
+            "#<lambda (x) (list x) x>"
+        );
+        assert_eq!(
+            show(&mut ctx, "(funcall (lambda (x) (list x) (* x 2)) 3)"),
+            "6"
+        );
+        assert_eq!(show(&mut ctx, "(funcall (lambda ()))"), "nil");
+        assert_eq!(show(&mut ctx, "(defun f (x) (list x) (+ x 1)) (f 1)"), "2");
+        assert_eq!(show(&mut ctx, "(defun g ()) (g)"), "nil");
+        assert_eq!(
+            show(&mut ctx, "(flet ((h (x) (list x) (* x 3))) (h 2))"),
+            "6"
+        );
+        assert_eq!(show(&mut ctx, "(funcall 'car '((1 2)))"), "(1 2)");
+        assert_eq!(show(&mut ctx, "(apply '+ 1 2 '(3 4))"), "10");
+        assert_eq!(
+            show(&mut ctx, "(apply (lambda (a b) (list b a)) '(x (y)))"),
+            "((y) x)"
+        );
+        assert_eq!(show(&mut ctx, "(apply 'list 1 ())"), "(1)");
+        assert_eq!(
+            show(&mut ctx, "(mapcar (lambda (x) (* x x)) '(1 2 3))"),
+            "(1 4 9)"
+        );
+        assert_eq!(show(&mut ctx, "(mapcar 'car '((a) (b c)))"), "(a b)");
+        assert!(matches!(
+            eval_str(&mut ctx, "(funcall (lambda (x) x))"),
+            Err(EvalError::ArgumentNumber { exp: 1, got: 0 })
+        ));
+        assert!(matches!(
+            eval_str(&mut ctx, "(funcall 'quote 1)"),
+            Err(EvalError::InvalidFunction { .. })
+        ));
+        assert!(matches!(
+            eval_str(&mut ctx, "((list 1) 2)"),
+            Err(EvalError::InvalidFunction { .. })
+        ));
+        assert!(matches!(
+            eval_str(&mut ctx, "(apply 'list 1 2)"),
+            Err(EvalError::WrongTypeArgumentList)
+        ));
+    }
+
+    #[test]
+    fn should_close_over_bindings() {
+        let mut ctx = Context::default();
+        eval_str(&mut ctx, "(defun make-adder (n) (lambda (x) (+ x n)))").unwrap();
+        assert_eq!(show(&mut ctx, "(funcall (make-adder 2) 3)"), "5");
+        assert_eq!(show(&mut ctx, "((make-adder 2) 3)"), "5");
+        assert_eq!(show(&mut ctx, "(eval (list (lambda (x) (* x 2)) 4))"), "8");
+        assert_eq!(show(&mut ctx, "(eval `(,(make-adder 2) (+ 1 2)))"), "5");
+        // The closure sees `n` where it was made, not where it is called
+        eval_str(&mut ctx, "(defun call-with-n (f n) (funcall f 1))").unwrap();
+        assert_eq!(show(&mut ctx, "(call-with-n (make-adder 10) 100)"), "11");
+        eval_str(
+            &mut ctx,
+            "(defun compose (f g) (lambda (x) (funcall f (funcall g x))))",
+        )
+        .unwrap();
+        assert_eq!(
+            show(
+                &mut ctx,
+                "(funcall (compose (make-adder 1) (lambda (x) (* x 10))) 4)"
+            ),
+            "41"
+        );
+        assert_eq!(
+            show(
+                &mut ctx,
+                "(list (funcall (make-adder 1) 0) (funcall (make-adder 2) 0))"
+            ),
+            "(1 2)"
+        );
+    }
 }
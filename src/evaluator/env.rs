@@ -0,0 +1,78 @@
+use super::Function;
+use crate::parser::Expr;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+/// A frame of bindings, in both the variable and function namespaces, which
+/// is looked up before its parent frames. The outermost frame holds the
+/// global bindings.
+#[derive(Default)]
+pub(super) struct Env {
+    vars: RefCell<HashMap<String, Expr>>,
+    funcs: RefCell<HashMap<String, Rc<Function>>>,
+    parent: Option<Rc<Env>>,
+}
+
+impl Env {
+    /// An empty frame enclosed in `parent`.
+    pub fn child(parent: &Rc<Env>) -> Rc<Env> {
+        Rc::new(Env {
+            parent: Some(Rc::clone(parent)),
+            ..Env::default()
+        })
+    }
+
+    /// Frames from this one to the global one.
+    fn frames(&self) -> impl Iterator<Item = &Env> {
+        std::iter::successors(Some(self), |env| env.parent.as_deref())
+    }
+
+    pub fn lookup_var(&self, name: &str) -> Option<Expr> {
+        self.frames()
+            .find_map(|env| env.vars.borrow().get(name).cloned())
+    }
+
+    /// Bind a variable in this frame, shadowing any outer binding.
+    pub fn define_var(&self, name: &str, value: Expr) {
+        self.vars.borrow_mut().insert(name.to_owned(), value);
+    }
+
+    /// Change the innermost binding of a variable, false if it is not bound
+    /// in any frame.
+    pub fn assign_var(&self, name: &str, value: Expr) -> bool {
+        match self
+            .frames()
+            .find(|env| env.vars.borrow().contains_key(name))
+        {
+            Some(env) => {
+                env.vars.borrow_mut().insert(name.to_owned(), value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn lookup_function(&self, name: &str) -> Option<Rc<Function>> {
+        self.frames()
+            .find_map(|env| env.funcs.borrow().get(name).cloned())
+    }
+
+    /// Bind a function in this frame, shadowing any outer binding.
+    pub fn define_function(&self, name: &str, function: Rc<Function>) {
+        self.funcs.borrow_mut().insert(name.to_owned(), function);
+    }
+}
+
+impl fmt::Debug for Env {
+    // Only the names: functions refer to the environment they are defined
+    // in, which may well be this one
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Env")
+            .field("vars", &self.vars.borrow().keys().collect::<Vec<_>>())
+            .field("funcs", &self.funcs.borrow().keys().collect::<Vec<_>>())
+            .field("parent", &self.parent)
+            .finish()
+    }
+}
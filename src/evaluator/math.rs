@@ -0,0 +1,578 @@
+// Math library. Functions follow the promotion rules of `numeric`: integer
+// results stay exact when the arguments are exact, a float argument gives a
+// float, and only complex arguments give complex results. Real arguments out
+// of a function's domain, like `(sqrt -1)`, are errors rather than NaNs.
+// Decimals are only accepted where the result is exact, see
+// `numeric::to_double` for explicit conversions.
+
+use super::builtins::{Arity, Registry};
+use super::numeric::{self, is_inexact, to_complex, to_ratio};
+use super::{Context, EvalError, Result};
+use crate::parser::{Expr, Num};
+use num_bigint::BigInt;
+use num_complex::Complex64;
+use num_integer::Integer;
+use num_rational::BigRational;
+use num_traits::{One, Signed, Zero};
+use rust_decimal::Decimal;
+use std::cmp::Ordering;
+
+pub(super) fn register(registry: &mut Registry) {
+    registry
+        .function("%", Arity::Exact(2), rem)
+        .function("rem", Arity::Exact(2), rem)
+        .function("mod", Arity::Exact(2), modulo)
+        .function("abs", Arity::Exact(1), abs)
+        .function("min", Arity::AtLeast(1), min)
+        .function("max", Arity::AtLeast(1), max)
+        .function("expt", Arity::Exact(2), expt)
+        .function("sqrt", Arity::Exact(1), sqrt)
+        .function("exp", Arity::Exact(1), exp)
+        .function("log", Arity::Between(1, 2), log)
+        .function("floor", Arity::Between(1, 2), floor)
+        .function("ceiling", Arity::Between(1, 2), ceiling)
+        .function("round", Arity::Between(1, 2), round)
+        .function("truncate", Arity::Between(1, 2), truncate)
+        .function("sin", Arity::Exact(1), sin)
+        .function("cos", Arity::Exact(1), cos)
+        .function("tan", Arity::Exact(1), tan)
+        .function("atan", Arity::Between(1, 2), atan)
+        .function("gcd", Arity::AtLeast(0), gcd)
+        .function("lcm", Arity::AtLeast(0), lcm);
+}
+
+/// How to round a quotient to an integer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rounding {
+    Floor,
+    Ceiling,
+    /// To the nearest integer, ties to even
+    Round,
+    Truncate,
+}
+
+fn domain_error(name: &str, arg: &Num) -> EvalError {
+    EvalError::Domain {
+        name: name.to_string(),
+        arg: arg.to_string(),
+    }
+}
+
+/// A real argument of the float function `name`. Decimals have to be
+/// converted explicitly.
+fn real_arg(name: &str, x: &Num) -> Result<f64> {
+    match x {
+        Num::Complex(_) => Err(EvalError::ShouldBeReal),
+        Num::Decimal(_) => Err(EvalError::DecimalArgument {
+            name: name.to_string(),
+        }),
+        real => Ok(real.to_f64()),
+    }
+}
+
+fn round_ratio(r: &BigRational, mode: Rounding) -> BigInt {
+    match mode {
+        Rounding::Floor => r.floor().to_integer(),
+        Rounding::Ceiling => r.ceil().to_integer(),
+        Rounding::Truncate => r.trunc().to_integer(),
+        Rounding::Round => {
+            let floor = r.floor();
+            let half = BigRational::new(BigInt::one(), BigInt::from(2));
+            let above = (r - &floor).cmp(&half);
+            let floor = floor.to_integer();
+            match above {
+                Ordering::Less => floor,
+                Ordering::Equal if floor.is_even() => floor,
+                _ => floor + 1,
+            }
+        }
+    }
+}
+
+/// Round a real number to an integer. A float stays a float.
+fn round_to_integer(x: &Num, mode: Rounding) -> Result<Num> {
+    match x {
+        Num::Int(_) | Num::Big(_) => Ok(x.clone()),
+        Num::Double(d) => Ok(Num::Double(match mode {
+            Rounding::Floor => d.floor(),
+            Rounding::Ceiling => d.ceil(),
+            Rounding::Round => d.round_ties_even(),
+            Rounding::Truncate => d.trunc(),
+        })),
+        exact => match to_ratio(exact) {
+            Some(r) => Ok(Num::from_big(round_ratio(&r, mode))),
+            None => Err(EvalError::ShouldBeReal),
+        },
+    }
+}
+
+/// `(floor x [divisor])` and friends: `x / divisor` rounded to an integer.
+fn divide_and_round(ctx: &mut Context, args: Vec<Expr>, mode: Rounding) -> Result<Expr> {
+    ctx.with_numbers(args, |num| match num {
+        [x] => round_to_integer(x, mode),
+        [x, divisor] => round_to_integer(&numeric::div(x, divisor)?, mode),
+        _ => unreachable!("arity is checked"),
+    })
+}
+
+fn floor(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    divide_and_round(ctx, args, Rounding::Floor)
+}
+
+fn ceiling(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    divide_and_round(ctx, args, Rounding::Ceiling)
+}
+
+fn round(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    divide_and_round(ctx, args, Rounding::Round)
+}
+
+fn truncate(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    divide_and_round(ctx, args, Rounding::Truncate)
+}
+
+/// Remainder of `x / y` rounded with `mode`: the sign of the result is the
+/// one of `x` when truncating, and the one of `y` when flooring.
+fn remainder(x: &Num, y: &Num, mode: Rounding) -> Result<Num> {
+    match (x, y) {
+        (Num::Int(a), Num::Int(b)) if *b != 0 => {
+            // Only `i64::MIN % -1` overflows, its remainder is 0
+            let r = a.checked_rem(*b).unwrap_or(0);
+            Ok(Num::Int(
+                if mode == Rounding::Floor && r != 0 && (r < 0) != (*b < 0) {
+                    r + b
+                } else {
+                    r
+                },
+            ))
+        }
+        (Num::Double(_), Num::Double(_) | Num::Int(_) | Num::Big(_) | Num::Ratio(_))
+        | (Num::Int(_) | Num::Big(_) | Num::Ratio(_), Num::Double(_)) => {
+            let (a, b) = (x.to_f64(), y.to_f64());
+            let r = a % b;
+            Ok(Num::Double(
+                if mode == Rounding::Floor && r != 0. && (r < 0.) != (b < 0.) {
+                    r + b
+                } else {
+                    r
+                },
+            ))
+        }
+        _ => {
+            if matches!(x, Num::Complex(_)) || matches!(y, Num::Complex(_)) {
+                return Err(EvalError::ShouldBeReal);
+            }
+            let quotient = round_to_integer(&numeric::div(x, y)?, mode)?;
+            numeric::sub(x, &numeric::mul(y, &quotient)?)
+        }
+    }
+}
+
+/// `(rem x y)`: remainder of the truncated division, also `%`
+fn rem(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    ctx.with_numbers(args, |num| remainder(&num[0], &num[1], Rounding::Truncate))
+}
+
+/// `(mod x y)`: remainder of the floored division
+fn modulo(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    ctx.with_numbers(args, |num| remainder(&num[0], &num[1], Rounding::Floor))
+}
+
+/// `(abs x)`: absolute value, the magnitude of a complex number
+fn abs(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    ctx.with_numbers(args, |num| Ok(numeric::magnitude(&num[0])))
+}
+
+/// The argument which is ordered `wanted` relative to all others. The result
+/// is a float if any argument is, and NaN if any argument is NaN.
+fn extremum(num: &[Num], wanted: Ordering) -> Result<Num> {
+    if num.iter().any(|x| matches!(x, Num::Complex(_))) {
+        return Err(EvalError::ShouldBeReal);
+    }
+    let mut best = &num[0];
+    for x in &num[1..] {
+        match numeric::compare(x, best)? {
+            Some(order) if order == wanted => best = x,
+            Some(_) => {}
+            None => return Ok(Num::Double(f64::NAN)),
+        }
+    }
+    if num.iter().any(|x| matches!(x, Num::Double(_))) {
+        numeric::to_double(best)
+    } else {
+        Ok(best.clone())
+    }
+}
+
+fn min(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    ctx.with_numbers(args, |num| extremum(num, Ordering::Less))
+}
+
+fn max(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    ctx.with_numbers(args, |num| extremum(num, Ordering::Greater))
+}
+
+/// Largest size in bits of an exact power. Beyond it `expt` is an
+/// `IntOverflow` error rather than running out of memory.
+const MAX_POWER_BITS: u64 = 1 << 20;
+
+/// `base` raised to an integer power, by repeated squaring so that exact
+/// bases give exact results.
+fn integer_power(base: &Num, power: i64) -> Result<Num> {
+    if let Num::Int(_) | Num::Big(_) | Num::Ratio(_) = base {
+        // A power of a b-bit integer has about (b - 1) * power bits, which
+        // is exact for powers of two and keeps 0, 1 and -1 unbounded
+        let r = to_ratio(base).unwrap_or_default();
+        let bits = r.numer().bits().max(r.denom().bits());
+        if bits.saturating_sub(1).saturating_mul(power.unsigned_abs()) > MAX_POWER_BITS {
+            return Err(EvalError::IntOverflow);
+        }
+    }
+    let mut result = match base {
+        Num::Double(_) => Num::Double(1.),
+        Num::Complex(_) => Num::Complex(Complex64::one()),
+        Num::Decimal(_) => Num::Decimal(Decimal::ONE),
+        _ => Num::Int(1),
+    };
+    let mut square = base.clone();
+    let mut n = power.unsigned_abs();
+    while n > 0 {
+        if n & 1 == 1 {
+            result = numeric::mul(&result, &square)?;
+        }
+        n >>= 1;
+        if n > 0 {
+            square = numeric::mul(&square, &square)?;
+        }
+    }
+    if power < 0 {
+        numeric::div(&Num::Int(1), &result)
+    } else {
+        Ok(result)
+    }
+}
+
+/// `(expt base power)`: exact for an exact base and an integer power, and
+/// complex if either is complex.
+fn expt(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    ctx.with_numbers(args, |num| {
+        let (base, power) = (&num[0], &num[1]);
+        match power {
+            Num::Int(p) => integer_power(base, *p),
+            _ if matches!(base, Num::Complex(_)) || matches!(power, Num::Complex(_)) => {
+                Ok(Num::Complex(to_complex(base).powc(to_complex(power))))
+            }
+            Num::Big(_) if !is_inexact(base) => Err(EvalError::IntOverflow),
+            _ => {
+                let (b, p) = (real_arg("expt", base)?, real_arg("expt", power)?);
+                if b < 0. && p.fract() != 0. {
+                    return Err(domain_error("expt", base));
+                }
+                Ok(Num::Double(b.powf(p)))
+            }
+        }
+    })
+}
+
+/// `(sqrt x)`: exact for the square of an exact number, e.g. `(sqrt 9/4)`
+fn sqrt(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    ctx.with_numbers(args, |num| {
+        let x = &num[0];
+        if let Num::Complex(c) = x {
+            return Ok(Num::Complex(c.sqrt()));
+        }
+        if let Num::Int(_) | Num::Big(_) | Num::Ratio(_) = x {
+            let r = to_ratio(x).unwrap_or_default();
+            if r.is_negative() {
+                return Err(domain_error("sqrt", x));
+            }
+            let (numer, denom) = (r.numer().sqrt(), r.denom().sqrt());
+            if &(&numer * &numer) == r.numer() && &(&denom * &denom) == r.denom() {
+                return Ok(Num::from_ratio(BigRational::new(numer, denom)));
+            }
+        }
+        let d = real_arg("sqrt", x)?;
+        if d < 0. {
+            return Err(domain_error("sqrt", x));
+        }
+        Ok(Num::Double(d.sqrt()))
+    })
+}
+
+/// Apply a float function, or its complex counterpart to a complex number.
+fn float_function(
+    ctx: &mut Context,
+    args: Vec<Expr>,
+    name: &str,
+    real: fn(f64) -> f64,
+    complex: fn(Complex64) -> Complex64,
+) -> Result<Expr> {
+    ctx.with_numbers(args, |num| match &num[0] {
+        Num::Complex(c) => Ok(Num::Complex(complex(*c))),
+        x => Ok(Num::Double(real(real_arg(name, x)?))),
+    })
+}
+
+fn exp(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    float_function(ctx, args, "exp", f64::exp, Complex64::exp)
+}
+
+fn sin(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    float_function(ctx, args, "sin", f64::sin, Complex64::sin)
+}
+
+fn cos(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    float_function(ctx, args, "cos", f64::cos, Complex64::cos)
+}
+
+fn tan(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    float_function(ctx, args, "tan", f64::tan, Complex64::tan)
+}
+
+/// Natural logarithm of a positive real, or of any non-zero complex.
+fn ln(x: &Num) -> Result<Num> {
+    match x {
+        Num::Complex(c) if c.is_zero() => Err(domain_error("log", x)),
+        Num::Complex(c) => Ok(Num::Complex(c.ln())),
+        real => {
+            let d = real_arg("log", real)?;
+            if d <= 0. {
+                return Err(domain_error("log", x));
+            }
+            Ok(Num::Double(d.ln()))
+        }
+    }
+}
+
+/// `(log x [base])`: natural logarithm, or logarithm in `base`
+fn log(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    ctx.with_numbers(args, |num| match num {
+        [x] => ln(x),
+        [x, base] => {
+            let ln_base = ln(base)?;
+            if ln_base == Num::Double(0.) {
+                return Err(domain_error("log", base));
+            }
+            numeric::div(&ln(x)?, &ln_base)
+        }
+        _ => unreachable!("arity is checked"),
+    })
+}
+
+/// `(atan y [x])`: arc tangent of `y`, or of `y / x` in the quadrant of the
+/// point `(x, y)`
+fn atan(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    ctx.with_numbers(args, |num| match num {
+        [Num::Complex(c)] => Ok(Num::Complex(c.atan())),
+        [y] => Ok(Num::Double(real_arg("atan", y)?.atan())),
+        [y, x] => Ok(Num::Double(
+            real_arg("atan", y)?.atan2(real_arg("atan", x)?),
+        )),
+        _ => unreachable!("arity is checked"),
+    })
+}
+
+fn integer_args(num: &[Num]) -> Result<Vec<BigInt>> {
+    num.iter()
+        .map(|x| match x {
+            Num::Int(i) => Ok(BigInt::from(*i)),
+            Num::Big(b) => Ok(b.clone()),
+            _ => Err(EvalError::ShouldBeInteger),
+        })
+        .collect()
+}
+
+/// `(gcd n...)`: greatest common divisor, 0 without arguments
+fn gcd(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    ctx.with_numbers(args, |num| {
+        let res = integer_args(num)?
+            .iter()
+            .fold(BigInt::zero(), |acc, n| acc.gcd(n));
+        Ok(Num::from_big(res))
+    })
+}
+
+/// `(lcm n...)`: least common multiple, 1 without arguments
+fn lcm(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    ctx.with_numbers(args, |num| {
+        let res = integer_args(num)?
+            .iter()
+            .fold(BigInt::one(), |acc, n| acc.lcm(n));
+        Ok(Num::from_big(res))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::evaluator::{eval_str, show, Context, EvalError};
+
+    #[test]
+    fn should_compute_remainders() {
+        let ctx = &mut Context::default();
+        assert_eq!(show(ctx, "(% 7 3)"), "1");
+        assert_eq!(show(ctx, "(rem -7 3)"), "-1");
+        assert_eq!(show(ctx, "(mod -7 3)"), "2");
+        assert_eq!(show(ctx, "(mod 7 -3)"), "-2");
+        assert_eq!(show(ctx, "(mod -9223372036854775808 -1)"), "0");
+        assert_eq!(show(ctx, "(rem 7.5 2)"), "1.5");
+        assert_eq!(show(ctx, "(mod -7.5 2)"), "0.5");
+        assert_eq!(show(ctx, "(mod 7/2 1)"), "1/2");
+        assert_eq!(show(ctx, "(mod -10.25m 3)"), "1.75m");
+        assert_eq!(show(ctx, "(rem 100000000000000000000 7)"), "2");
+        assert!(matches!(eval_str(ctx, "(mod 1 0)"), Err(EvalError::DivBy0)));
+        assert!(matches!(
+            eval_str(ctx, "(rem 1+i 2)"),
+            Err(EvalError::ShouldBeReal)
+        ));
+    }
+
+    #[test]
+    fn should_round_to_integers() {
+        let ctx = &mut Context::default();
+        assert_eq!(show(ctx, "(floor -7/2)"), "-4");
+        assert_eq!(show(ctx, "(ceiling -7/2)"), "-3");
+        assert_eq!(show(ctx, "(truncate -7/2)"), "-3");
+        assert_eq!(show(ctx, "(round 5/2)"), "2");
+        assert_eq!(show(ctx, "(round 7/2)"), "4");
+        assert_eq!(show(ctx, "(round -2.5)"), "-2.0");
+        assert_eq!(show(ctx, "(floor 2.7)"), "2.0");
+        assert_eq!(show(ctx, "(floor 2.75m)"), "2");
+        assert_eq!(show(ctx, "(floor 7 2)"), "3");
+        assert_eq!(show(ctx, "(ceiling 7 2)"), "4");
+        assert_eq!(show(ctx, "(truncate 5)"), "5");
+        assert!(matches!(
+            eval_str(ctx, "(floor 1 0)"),
+            Err(EvalError::DivBy0)
+        ));
+        assert!(matches!(
+            eval_str(ctx, "(round 1+i)"),
+            Err(EvalError::ShouldBeReal)
+        ));
+    }
+
+    #[test]
+    fn should_compare_numbers() {
+        let ctx = &mut Context::default();
+        assert_eq!(show(ctx, "(abs -5)"), "5");
+        assert_eq!(show(ctx, "(abs -5/3)"), "5/3");
+        assert_eq!(show(ctx, "(abs -2.5m)"), "2.5m");
+        assert_eq!(show(ctx, "(abs 3-4i)"), "5.0");
+        assert_eq!(show(ctx, "(min 3 1/2 2)"), "1/2");
+        assert_eq!(show(ctx, "(max 3 1/2 2)"), "3");
+        assert_eq!(show(ctx, "(max 1 2.5 2)"), "2.5");
+        assert_eq!(show(ctx, "(max 3 2.5)"), "3.0");
+        assert_eq!(show(ctx, "(min 7)"), "7");
+        assert_eq!(show(ctx, "(max 1 nan)"), "nan");
+        assert_eq!(show(ctx, "(min 1.5m 2)"), "1.5m");
+        assert!(matches!(
+            eval_str(ctx, "(max 1 2i)"),
+            Err(EvalError::ShouldBeReal)
+        ));
+        assert!(matches!(
+            eval_str(ctx, "(max 1.5m 2.0)"),
+            Err(EvalError::InexactDecimal)
+        ));
+    }
+
+    #[test]
+    fn should_raise_to_powers() {
+        let ctx = &mut Context::default();
+        assert_eq!(show(ctx, "(expt 2 10)"), "1024");
+        assert_eq!(show(ctx, "(expt 2 100)"), "1267650600228229401496703205376");
+        assert!(eval_str(ctx, "(expt 2 1000000)").is_ok());
+        assert_eq!(show(ctx, "(expt 1 10000000000)"), "1");
+        assert_eq!(show(ctx, "(expt -1 10000000001)"), "-1");
+        assert_eq!(show(ctx, "(expt 0 10000000000)"), "0");
+        for src in [
+            "(expt 2 10000000000)",
+            "(expt 3 -10000000000)",
+            "(expt 1/2 10000000000)",
+        ] {
+            assert!(matches!(eval_str(ctx, src), Err(EvalError::IntOverflow)));
+        }
+        assert_eq!(show(ctx, "(expt 2 -2)"), "1/4");
+        assert_eq!(show(ctx, "(expt 2/3 3)"), "8/27");
+        assert_eq!(show(ctx, "(expt 2.0 0)"), "1.0");
+        assert_eq!(show(ctx, "(expt 1.5m 2)"), "2.25m");
+        assert_eq!(show(ctx, "(expt 4 0.5)"), "2.0");
+        assert_eq!(show(ctx, "(expt 0+1i 2)"), "-1.0+0.0i");
+        assert_eq!(show(ctx, "(expt -2.0 3)"), "-8.0");
+        assert!(matches!(
+            eval_str(ctx, "(expt 0 -1)"),
+            Err(EvalError::DivBy0)
+        ));
+        assert!(matches!(
+            eval_str(ctx, "(expt -8 1/3)"),
+            Err(EvalError::Domain { .. })
+        ));
+        assert_eq!(show(ctx, "(sqrt 16)"), "4");
+        assert_eq!(show(ctx, "(sqrt 9/4)"), "3/2");
+        assert_eq!(show(ctx, "(sqrt 2)"), std::f64::consts::SQRT_2.to_string());
+        assert_eq!(show(ctx, "(sqrt 6.25)"), "2.5");
+        assert_eq!(show(ctx, "(sqrt -4+0i)"), "0.0+2.0i");
+        assert!(matches!(
+            eval_str(ctx, "(sqrt -4)"),
+            Err(EvalError::Domain { .. })
+        ));
+        assert!(matches!(
+            eval_str(ctx, "(sqrt 4m)"),
+            Err(EvalError::DecimalArgument { name }) if name == "sqrt"
+        ));
+        assert!(matches!(
+            eval_str(ctx, "(exp 0m)"),
+            Err(EvalError::DecimalArgument { name }) if name == "exp"
+        ));
+    }
+
+    #[test]
+    fn should_compute_transcendental_functions() {
+        let ctx = &mut Context::default();
+        assert_eq!(show(ctx, "(exp 0)"), "1.0");
+        assert_eq!(show(ctx, "(log 1)"), "0.0");
+        assert_eq!(show(ctx, "(log 100 10)"), "2.0");
+        assert_eq!(show(ctx, "(sin 0)"), "0.0");
+        assert_eq!(show(ctx, "(cos 0.0)"), "1.0");
+        assert_eq!(show(ctx, "(tan 0)"), "0.0");
+        assert_eq!(
+            show(ctx, "(atan 1 0)"),
+            std::f64::consts::FRAC_PI_2.to_string()
+        );
+        assert_eq!(show(ctx, "(atan 0)"), "0.0");
+        assert_eq!(show(ctx, "(exp 0+0i)"), "1.0+0.0i");
+        assert!(matches!(
+            eval_str(ctx, "(log 0)"),
+            Err(EvalError::Domain { .. })
+        ));
+        assert!(matches!(
+            eval_str(ctx, "(log -1)"),
+            Err(EvalError::Domain { .. })
+        ));
+        assert!(matches!(
+            eval_str(ctx, "(log 8 1)"),
+            Err(EvalError::Domain { .. })
+        ));
+        assert!(matches!(
+            eval_str(ctx, "(atan 1 2i)"),
+            Err(EvalError::ShouldBeReal)
+        ));
+    }
+
+    #[test]
+    fn should_compute_divisors() {
+        let ctx = &mut Context::default();
+        assert_eq!(show(ctx, "(gcd 12 18 -8)"), "2");
+        assert_eq!(show(ctx, "(gcd)"), "0");
+        assert_eq!(show(ctx, "(gcd 0 -5)"), "5");
+        assert_eq!(show(ctx, "(lcm 4 6 -10)"), "60");
+        assert_eq!(show(ctx, "(lcm)"), "1");
+        assert_eq!(show(ctx, "(lcm 3 0)"), "0");
+        assert_eq!(
+            show(ctx, "(lcm 4294967296 4294967297)"),
+            "18446744078004518912"
+        );
+        assert!(matches!(
+            eval_str(ctx, "(gcd 1 2.0)"),
+            Err(EvalError::ShouldBeInteger)
+        ));
+    }
+}
@@ -1,44 +1,193 @@
+mod bitwise;
+mod builtins;
+mod conditionals;
 mod context;
+mod env;
+mod math;
+mod numeric;
+mod variables;
 
-use crate::parser::Expr;
+use crate::parser::{Atom, Bool, Expr};
+use builtins::{Builtin, Registry};
 use custom_error::custom_error;
-use std::collections::HashMap;
+use env::Env;
+use std::fmt;
+use std::rc::Rc;
 
 custom_error! {
     pub EvalError
     ArgumentNumber{exp: usize, got: usize} = "Wrong number of arguments, expected {exp}, got {got}",
+    TooFewArguments{min: usize, got: usize} = "Wrong number of arguments, expected at least {min}, got {got}",
+    TooManyArguments{max: usize, got: usize} = "Wrong number of arguments, expected at most {max}, got {got}",
     InvalidArguments{args: String} = "Invalid arguments for function: {args}",
     VoidFunction{name: String} = "Function `{name}` not found",
     VoidVariable{name: String} = "Variable `{name}` not found",
+    SetVoidVariable{name: String} = "Cannot set `{name}`, it is not defined: use `defvar` or `let` first",
     ShouldBeNum = "Argument should be number",
+    ShouldBeRational = "Argument should be a rational number",
+    ShouldBeReal = "Argument should be a real number",
+    ShouldBeInteger = "Argument should be an integer",
+    Domain{name: String, arg: String} = "Argument {arg} is out of the domain of `{name}`",
     InvalidVarName = "Invalid variable name",
-    Unimplemented{name: String} = "Built-in `{name}` not implemented",
-    InvalidFunction{function: String} = "Invalid function `{}`",
+    InvalidFunction{function: String} = "Invalid function `{function}`",
     IntOverflow = "Integer overflow",
+    DecimalOverflow = "Decimal overflow",
+    InexactDecimal = "Decimals and floats cannot be mixed, convert with `decimal` or `double`",
+    DecimalArgument{name: String} = "`{name}` does not take decimals, convert with `double` first",
     DivBy0 = "Division by 0",
+    DepthExceeded{max: usize} = "Forms nested more than {max} deep, possibly an infinite recursion",
     InvalidSyntax = "Invalid syntax",
-    WrongTypeArgumentList = "Wrong type argument, expected list"
+    WrongTypeArgumentList = "Wrong type argument, expected list",
+    UnquoteOutsideQuasiquote = "Unquote outside of a quasiquote",
+    SpliceOutsideList = "Unquote-splicing outside of a list"
 }
 
 type Result<T> = std::result::Result<T, EvalError>;
 
-#[derive(Default, Debug)]
+/// Whether a value counts as true in a condition. `nil` and the empty list
+/// are false, everything else is true.
+pub fn truthy(value: &Expr) -> bool {
+    match value {
+        Expr::Atomic(Atom::Boolean(Bool::Nil)) => false,
+        Expr::List(items) => !items.is_empty(),
+        _ => true,
+    }
+}
+
+/// The elements of a list value, `nil` standing for the empty list.
+fn as_list(value: &Expr) -> Option<&[Expr]> {
+    match value {
+        Expr::List(items) => Some(items),
+        Expr::Atomic(Atom::Boolean(Bool::Nil)) => Some(&[]),
+        _ => None,
+    }
+}
+
+/// Rebuild `expr` with `f` applied to each of its atoms.
+fn map_atoms(expr: &Expr, f: &impl Fn(&Atom) -> Atom) -> Expr {
+    let map_box = |e: &Expr| Box::new(map_atoms(e, f));
+    match expr {
+        Expr::Atomic(atom) => Expr::Atomic(f(atom)),
+        Expr::Qexpr(e) => Expr::Qexpr(map_box(e)),
+        Expr::Quasiquote(e) => Expr::Quasiquote(map_box(e)),
+        Expr::Unquote(e) => Expr::Unquote(map_box(e)),
+        Expr::UnquoteSplicing(e) => Expr::UnquoteSplicing(map_box(e)),
+        Expr::List(items) => Expr::List(items.iter().map(|e| map_atoms(e, f)).collect()),
+        Expr::Function(_) => expr.clone(),
+    }
+}
+
+/// Turn quoted code into data: names become symbols.
+fn datum(expr: &Expr) -> Expr {
+    map_atoms(expr, &|atom| match atom {
+        Atom::Name(name) => Atom::Symbol(name.clone()),
+        atom => atom.clone(),
+    })
+}
+
+/// Turn data back into code that can be evaluated: symbols become names.
+fn code(expr: &Expr) -> Expr {
+    map_atoms(expr, &|atom| match atom {
+        Atom::Symbol(name) => Atom::Name(name.clone()),
+        atom => atom.clone(),
+    })
+}
+
+/// Evaluate every form of `src` and return the value of the last one.
+#[cfg(test)]
+fn eval_str(ctx: &mut Context, src: &str) -> Result<Expr> {
+    let mut res = Err(EvalError::InvalidSyntax);
+    for form in crate::parser::parse_program(src).expect("Parsing error") {
+        res = ctx.eval_ast(&form);
+    }
+    res
+}
+
+/// The printed value of the last form of `src`, which must evaluate without
+/// error.
+#[cfg(test)]
+fn show(ctx: &mut Context, src: &str) -> String {
+    eval_str(ctx, src).unwrap().to_string()
+}
+
+/// The canonical value for a boolean: `t` or `nil`.
+fn boolean(value: bool) -> Expr {
+    Expr::Atomic(Atom::Boolean(if value { Bool::True } else { Bool::Nil }))
+}
+
+#[derive(Debug)]
 pub struct Context {
-    vars: HashMap<String, Expr>,
-    funcs: HashMap<String, Function>,
+    /// Global variables and functions
+    globals: Rc<Env>,
+    /// Innermost frame of the code being evaluated
+    env: Rc<Env>,
+    builtins: Registry,
+    /// Number of forms being evaluated, one inside the other
+    depth: usize,
     debug: bool,
 }
 
+/// Deepest nesting of forms being evaluated, e.g. of recursive calls, before
+/// evaluation stops with a `DepthExceeded` error rather than overflowing the
+/// stack.
+pub const MAX_DEPTH: usize = 10_000;
+
+/// Stack size of a thread that evaluates code, enough for `MAX_DEPTH` even
+/// in debug builds.
+pub const STACK_SIZE: usize = 256 * 1024 * 1024;
+
 impl Context {
     pub fn new(debug: bool) -> Self {
-        let mut ctx = Context::default();
-        ctx.debug = debug;
-        ctx
+        let globals = Rc::new(Env::default());
+        Context {
+            env: Rc::clone(&globals),
+            globals,
+            builtins: builtins::standard(),
+            depth: 0,
+            debug,
+        }
     }
 }
 
-#[derive(Clone, Debug)]
-struct Function {
+impl Default for Context {
+    fn default() -> Self {
+        Context::new(false)
+    }
+}
+
+#[derive(Debug)]
+pub struct Function {
     args: Vec<String>,
-    body: Expr,
+    /// Forms evaluated in order, the last one gives the result
+    body: Vec<Expr>,
+    /// Where the function is defined, its body sees the bindings in scope
+    /// there. A function bound in that same environment, like a `defun` at
+    /// top level or a `letrec` closure, makes a reference cycle which is
+    /// never freed. This leak is accepted: such environments mostly live as
+    /// long as the session anyway.
+    env: Rc<Env>,
+}
+
+/// Functions are only equal to themselves.
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#<lambda ({})", self.args.join(" "))?;
+        for form in &self.body {
+            write!(f, " {}", form)?;
+        }
+        write!(f, ">")
+    }
+}
+
+/// What a name in the function namespace resolves to.
+#[derive(Clone, Debug)]
+enum Callable {
+    User(Rc<Function>),
+    Builtin(Builtin),
 }
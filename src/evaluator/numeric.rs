@@ -0,0 +1,431 @@
+// Arithmetic on numbers. Exact arithmetic stays exact: operations on `Int`s
+// that overflow are carried out on big integers, integer division yields
+// ratios, and results are demoted back to the smallest exact representation,
+// see `Num::from_ratio`. A `Double` operand makes the result a `Double`, and
+// a `Complex` one makes it a `Complex`.
+//
+// Decimals are exact too: mixed with integers they stay decimals, mixed with
+// ratios they become ratios. They are never mixed with floats implicitly,
+// see `to_decimal` and `to_double`.
+
+use super::{EvalError, Result};
+use crate::parser::Num;
+use num_bigint::BigInt;
+use num_complex::Complex64;
+use num_rational::BigRational;
+use num_traits::{Signed, ToPrimitive};
+use rust_decimal::{Decimal, RoundingStrategy};
+use std::cmp::Ordering;
+
+/// An arithmetic operation, for each representation of its operands.
+struct Op {
+    /// On two `Int`s, `None` when the result does not fit
+    fixnum: fn(i64, i64) -> Option<i64>,
+    exact: fn(BigRational, BigRational) -> BigRational,
+    /// `None` when the result does not fit
+    decimal: fn(Decimal, Decimal) -> Option<Decimal>,
+    double: fn(f64, f64) -> f64,
+    complex: fn(Complex64, Complex64) -> Complex64,
+}
+
+const ADD: Op = Op {
+    fixnum: i64::checked_add,
+    exact: |a, b| a + b,
+    decimal: Decimal::checked_add,
+    double: |a, b| a + b,
+    complex: |a, b| a + b,
+};
+
+const SUB: Op = Op {
+    fixnum: i64::checked_sub,
+    exact: |a, b| a - b,
+    decimal: Decimal::checked_sub,
+    double: |a, b| a - b,
+    complex: |a, b| a - b,
+};
+
+const MUL: Op = Op {
+    fixnum: i64::checked_mul,
+    exact: |a, b| a * b,
+    decimal: Decimal::checked_mul,
+    double: |a, b| a * b,
+    complex: |a, b| a * b,
+};
+
+const DIV: Op = Op {
+    fixnum: |a, b| a.checked_rem(b).filter(|r| *r == 0).and(a.checked_div(b)),
+    exact: |a, b| a / b,
+    decimal: Decimal::checked_div,
+    double: |a, b| a / b,
+    complex: |a, b| a / b,
+};
+
+pub(super) fn is_inexact(num: &Num) -> bool {
+    matches!(num, Num::Double(_) | Num::Complex(_))
+}
+
+/// The exact value of a number, `None` for a `Double` or a `Complex`.
+pub(super) fn to_ratio(num: &Num) -> Option<BigRational> {
+    match num {
+        Num::Double(_) | Num::Complex(_) => None,
+        Num::Int(i) => Some(BigRational::from_integer(BigInt::from(*i))),
+        Num::Big(b) => Some(BigRational::from_integer(b.clone())),
+        Num::Ratio(r) => Some(r.clone()),
+        Num::Decimal(d) => Some(BigRational::new(
+            BigInt::from(d.mantissa()),
+            BigInt::from(10).pow(d.scale()),
+        )),
+    }
+}
+
+pub(super) fn to_complex(num: &Num) -> Complex64 {
+    match num {
+        Num::Complex(c) => *c,
+        real => Complex64::new(real.to_f64(), 0.),
+    }
+}
+
+/// An integer or a decimal as a decimal, `None` for other numbers or if it
+/// does not fit.
+fn as_decimal(num: &Num) -> Option<Decimal> {
+    match num {
+        Num::Decimal(d) => Some(*d),
+        Num::Int(i) => Some(Decimal::from(*i)),
+        Num::Big(b) => b
+            .to_i128()
+            .and_then(|i| Decimal::try_from_i128_with_scale(i, 0).ok()),
+        _ => None,
+    }
+}
+
+/// Apply an operation with the representation the operands call for: `Int`s
+/// as long as the result fits, then exact ratios or decimals, `Double` as
+/// soon as one of them is a `Double` and `Complex` as soon as one of them is
+/// a `Complex`.
+fn arith(x: &Num, y: &Num, op: &Op) -> Result<Num> {
+    if let (Num::Int(a), Num::Int(b)) = (x, y) {
+        if let Some(res) = (op.fixnum)(*a, *b) {
+            return Ok(Num::Int(res));
+        }
+    }
+    let decimal = matches!(x, Num::Decimal(_)) || matches!(y, Num::Decimal(_));
+    if decimal && (is_inexact(x) || is_inexact(y)) {
+        return Err(EvalError::InexactDecimal);
+    }
+    if matches!(x, Num::Complex(_)) || matches!(y, Num::Complex(_)) {
+        return Ok(Num::Complex((op.complex)(to_complex(x), to_complex(y))));
+    }
+    if decimal && !matches!(x, Num::Ratio(_)) && !matches!(y, Num::Ratio(_)) {
+        return match (as_decimal(x), as_decimal(y)) {
+            (Some(a), Some(b)) => (op.decimal)(a, b)
+                .map(Num::Decimal)
+                .ok_or(EvalError::DecimalOverflow),
+            _ => Err(EvalError::DecimalOverflow),
+        };
+    }
+    match (to_ratio(x), to_ratio(y)) {
+        (Some(a), Some(b)) => Ok(Num::from_ratio((op.exact)(a, b))),
+        _ => Ok(Num::Double((op.double)(x.to_f64(), y.to_f64()))),
+    }
+}
+
+pub fn add(x: &Num, y: &Num) -> Result<Num> {
+    arith(x, y, &ADD)
+}
+
+pub fn sub(x: &Num, y: &Num) -> Result<Num> {
+    arith(x, y, &SUB)
+}
+
+pub fn mul(x: &Num, y: &Num) -> Result<Num> {
+    arith(x, y, &MUL)
+}
+
+/// Exact division, giving a ratio unless the quotient is an integer, and a
+/// decimal rounded to 28 digits between decimals. Dividing an exact number
+/// by zero is an error, a `Double` or `Complex` division follows IEEE 754.
+pub fn div(x: &Num, y: &Num) -> Result<Num> {
+    let zero = match y {
+        Num::Int(i) => *i == 0,
+        Num::Decimal(d) => d.is_zero(),
+        _ => false,
+    };
+    if zero && !is_inexact(x) {
+        return Err(EvalError::DivBy0);
+    }
+    arith(x, y, &DIV)
+}
+
+/// Order of two real numbers, `None` if one of them is a NaN. Exact numbers
+/// are compared exactly.
+pub fn compare(x: &Num, y: &Num) -> Result<Option<Ordering>> {
+    if matches!(x, Num::Complex(_)) || matches!(y, Num::Complex(_)) {
+        return Err(EvalError::ShouldBeReal);
+    }
+    if let (Num::Int(a), Num::Int(b)) = (x, y) {
+        return Ok(Some(a.cmp(b)));
+    }
+    let decimal = matches!(x, Num::Decimal(_)) || matches!(y, Num::Decimal(_));
+    if decimal && (is_inexact(x) || is_inexact(y)) {
+        return Err(EvalError::InexactDecimal);
+    }
+    match (to_ratio(x), to_ratio(y)) {
+        (Some(a), Some(b)) => Ok(Some(a.cmp(&b))),
+        _ => Ok(x.to_f64().partial_cmp(&y.to_f64())),
+    }
+}
+
+/// A real number as a decimal, rounded to 28 digits if needed. Floats are
+/// converted from their shortest representation, so that `0.1` gives `0.1m`.
+pub fn to_decimal(x: &Num) -> Result<Num> {
+    decimal_value(x).map(Num::Decimal)
+}
+
+fn decimal_value(x: &Num) -> Result<Decimal> {
+    let decimal = match x {
+        Num::Complex(_) => return Err(EvalError::ShouldBeReal),
+        Num::Double(d) if !d.is_finite() => {
+            return Err(EvalError::Domain {
+                name: "decimal".to_string(),
+                arg: x.to_string(),
+            })
+        }
+        Num::Double(d) => Decimal::from_str_exact(&d.to_string())
+            .ok()
+            .or_else(|| Decimal::from_f64_retain(*d)),
+        Num::Ratio(r) => as_decimal(&Num::from_big(r.numer().clone()))
+            .zip(as_decimal(&Num::from_big(r.denom().clone())))
+            .and_then(|(n, d)| n.checked_div(d)),
+        exact => as_decimal(exact),
+    };
+    decimal.ok_or(EvalError::DecimalOverflow)
+}
+
+/// A real number as the nearest float.
+pub fn to_double(x: &Num) -> Result<Num> {
+    match x {
+        Num::Complex(_) => Err(EvalError::ShouldBeReal),
+        real => Ok(Num::Double(real.to_f64())),
+    }
+}
+
+/// The rounding strategy for the name of a rounding mode.
+pub fn rounding_mode(name: &str) -> Option<RoundingStrategy> {
+    match name {
+        "half-even" => Some(RoundingStrategy::MidpointNearestEven),
+        "half-up" => Some(RoundingStrategy::MidpointAwayFromZero),
+        "truncate" => Some(RoundingStrategy::ToZero),
+        _ => None,
+    }
+}
+
+/// Round an exact number to a decimal with `places` digits after the point.
+pub fn round_decimal(x: &Num, places: u32, mode: RoundingStrategy) -> Result<Num> {
+    if is_inexact(x) {
+        return Err(EvalError::InexactDecimal);
+    }
+    Ok(Num::Decimal(
+        decimal_value(x)?.round_dp_with_strategy(places, mode),
+    ))
+}
+
+/// Numerator of a rational, in lowest terms, `None` for other numbers.
+pub fn numerator(x: &Num) -> Option<Num> {
+    to_ratio(x).map(|r| Num::from_big(r.numer().clone()))
+}
+
+/// Denominator of a rational, in lowest terms, `None` for other numbers.
+pub fn denominator(x: &Num) -> Option<Num> {
+    to_ratio(x).map(|r| Num::from_big(r.denom().clone()))
+}
+
+pub fn real_part(x: &Num) -> Num {
+    match x {
+        Num::Complex(c) => Num::Double(c.re),
+        real => real.clone(),
+    }
+}
+
+/// The imaginary part, 0 for a real number, a float unless it is exact.
+pub fn imag_part(x: &Num) -> Num {
+    match x {
+        Num::Complex(c) => Num::Double(c.im),
+        Num::Double(_) => Num::Double(0.),
+        _ => Num::Int(0),
+    }
+}
+
+/// Absolute value of a real number, modulus of a complex one.
+pub fn magnitude(x: &Num) -> Num {
+    match x {
+        Num::Complex(c) => Num::Double(c.norm()),
+        Num::Double(d) => Num::Double(d.abs()),
+        Num::Decimal(d) => Num::Decimal(d.abs()),
+        Num::Int(i) if *i != i64::MIN => Num::Int(i.abs()),
+        exact => Num::from_ratio(to_ratio(exact).unwrap_or_default().abs()),
+    }
+}
+
+/// Argument of a number in radians, between -pi and pi.
+pub fn angle(x: &Num) -> Num {
+    Num::Double(match x {
+        Num::Complex(c) => c.arg(),
+        real => 0f64.atan2(real.to_f64()),
+    })
+}
+
+pub fn conjugate(x: &Num) -> Num {
+    match x {
+        Num::Complex(c) => Num::Complex(c.conj()),
+        real => real.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn big(s: &str) -> Num {
+        Num::from_big(s.parse().unwrap())
+    }
+
+    fn ratio(n: i64, d: i64) -> Num {
+        Num::from_ratio(BigRational::new(n.into(), d.into()))
+    }
+
+    #[test]
+    fn should_promote_on_overflow() {
+        let max = Num::Int(i64::MAX);
+        assert_eq!(add(&max, &Num::Int(1)).unwrap(), big("9223372036854775808"));
+        assert_eq!(
+            mul(&max, &max).unwrap(),
+            big("85070591730234615847396907784232501249")
+        );
+        assert_eq!(
+            sub(&Num::Int(i64::MIN), &Num::Int(1)).unwrap(),
+            big("-9223372036854775809")
+        );
+        assert_eq!(
+            div(&Num::Int(i64::MIN), &Num::Int(-1)).unwrap(),
+            big("9223372036854775808")
+        );
+    }
+
+    #[test]
+    fn should_demote_when_fitting() {
+        let past_max = add(&Num::Int(i64::MAX), &Num::Int(1)).unwrap();
+        assert!(matches!(past_max, Num::Big(_)));
+        assert_eq!(sub(&past_max, &Num::Int(1)).unwrap(), Num::Int(i64::MAX));
+        assert_eq!(div(&past_max, &past_max).unwrap(), Num::Int(1));
+        assert_eq!(
+            add(&past_max, &Num::Double(0.5)).unwrap(),
+            Num::Double(2f64.powi(63))
+        );
+        assert!(matches!(
+            div(&past_max, &Num::Int(0)),
+            Err(EvalError::DivBy0)
+        ));
+        assert_eq!(
+            div(&Num::Int(1), &Num::Double(0.)).unwrap(),
+            Num::Double(f64::INFINITY)
+        );
+    }
+
+    #[test]
+    fn should_divide_exactly() {
+        let third = div(&Num::Int(1), &Num::Int(3)).unwrap();
+        assert_eq!(third, ratio(1, 3));
+        assert_eq!(div(&Num::Int(6), &Num::Int(-3)).unwrap(), Num::Int(-2));
+        assert_eq!(add(&third, &ratio(2, 3)).unwrap(), Num::Int(1));
+        assert_eq!(mul(&third, &Num::Int(6)).unwrap(), Num::Int(2));
+        assert_eq!(sub(&third, &Num::Int(1)).unwrap(), ratio(-2, 3));
+        assert_eq!(div(&third, &ratio(2, 9)).unwrap(), ratio(3, 2));
+        assert_eq!(
+            add(&ratio(1, 4), &Num::Double(0.5)).unwrap(),
+            Num::Double(0.75)
+        );
+        assert_eq!(
+            add(&big("18446744073709551616"), &ratio(1, 2))
+                .unwrap()
+                .to_string(),
+            "36893488147419103233/2"
+        );
+        assert!(matches!(div(&third, &Num::Int(0)), Err(EvalError::DivBy0)));
+        assert_eq!(numerator(&ratio(-6, 4)), Some(Num::Int(-3)));
+        assert_eq!(denominator(&ratio(-6, 4)), Some(Num::Int(2)));
+        assert_eq!(denominator(&Num::Int(5)), Some(Num::Int(1)));
+        assert_eq!(numerator(&Num::Double(0.5)), None);
+    }
+
+    #[test]
+    fn should_spread_complex() {
+        let c = |re, im| Num::Complex(Complex64::new(re, im));
+        let z = c(3., 4.);
+        assert_eq!(add(&z, &Num::Int(1)).unwrap(), c(4., 4.));
+        assert_eq!(sub(&ratio(1, 2), &z).unwrap(), c(-2.5, -4.));
+        assert_eq!(mul(&z, &conjugate(&z)).unwrap(), c(25., 0.));
+        assert_eq!(div(&z, &Num::Int(2)).unwrap(), c(1.5, 2.));
+        assert_eq!(mul(&c(0., 1.), &c(0., 1.)).unwrap(), c(-1., 0.));
+        assert_eq!(magnitude(&z), Num::Double(5.));
+        assert_eq!(magnitude(&ratio(-1, 2)), ratio(1, 2));
+        assert_eq!(magnitude(&Num::Int(i64::MIN)), big("9223372036854775808"));
+        assert_eq!(real_part(&z), Num::Double(3.));
+        assert_eq!(imag_part(&z), Num::Double(4.));
+        assert_eq!(imag_part(&Num::Int(7)), Num::Int(0));
+        assert_eq!(angle(&c(0., 2.)), Num::Double(std::f64::consts::FRAC_PI_2));
+        assert_eq!(angle(&Num::Int(-1)), Num::Double(std::f64::consts::PI));
+    }
+
+    #[test]
+    fn should_keep_decimals_exact() {
+        let dec = |s: &str| Num::Decimal(Decimal::from_str_exact(s).unwrap());
+        assert_eq!(add(&dec("0.1"), &dec("0.2")).unwrap(), dec("0.3"));
+        assert_eq!(
+            mul(&dec("1.10"), &Num::Int(3)).unwrap().to_string(),
+            "3.30m"
+        );
+        assert_eq!(div(&dec("1"), &Num::Int(8)).unwrap(), dec("0.125"));
+        assert_eq!(sub(&dec("0.5"), &ratio(1, 3)).unwrap(), ratio(1, 6));
+        assert!(matches!(
+            add(&dec("1"), &Num::Double(1.)),
+            Err(EvalError::InexactDecimal)
+        ));
+        assert!(matches!(
+            mul(&dec("79228162514264337593543950335"), &Num::Int(2)),
+            Err(EvalError::DecimalOverflow)
+        ));
+        assert!(matches!(
+            div(&Num::Int(1), &dec("0.00")),
+            Err(EvalError::DivBy0)
+        ));
+        let half_even = rounding_mode("half-even").unwrap();
+        let half_up = rounding_mode("half-up").unwrap();
+        let truncate = rounding_mode("truncate").unwrap();
+        assert_eq!(
+            round_decimal(&dec("0.125"), 2, half_even).unwrap(),
+            dec("0.12")
+        );
+        assert_eq!(
+            round_decimal(&dec("0.135"), 2, half_even).unwrap(),
+            dec("0.14")
+        );
+        assert_eq!(
+            round_decimal(&dec("-0.125"), 2, half_up).unwrap(),
+            dec("-0.13")
+        );
+        assert_eq!(
+            round_decimal(&dec("0.129"), 2, truncate).unwrap(),
+            dec("0.12")
+        );
+        assert_eq!(to_decimal(&Num::Double(0.1)).unwrap(), dec("0.1"));
+        assert_eq!(
+            to_decimal(&ratio(1, 3)).unwrap().to_string(),
+            "0.3333333333333333333333333333m"
+        );
+        assert_eq!(to_double(&dec("2.5")).unwrap(), Num::Double(2.5));
+        assert!(matches!(
+            to_decimal(&Num::Double(f64::NAN)),
+            Err(EvalError::Domain { .. })
+        ));
+    }
+}
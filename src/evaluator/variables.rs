@@ -0,0 +1,266 @@
+// Forms defining and assigning variables. Globals are defined with `defvar`
+// and `defparameter`, locals with `let`, `let*` and `letrec`, and `setq`
+// changes the innermost binding of a name, which must exist.
+
+use super::builtins::{Arity, Registry};
+use super::env::Env;
+use super::{Context, EvalError, Result};
+use crate::parser::{Atom, Bool, Expr};
+use std::rc::Rc;
+
+pub(super) fn register(registry: &mut Registry) {
+    registry
+        .special_form("defvar", Arity::Between(1, 2), defvar)
+        .special_form("defparameter", Arity::Exact(2), defparameter)
+        .special_form("setq", Arity::AtLeast(0), setq)
+        .special_form("set!", Arity::Exact(2), setq)
+        .special_form("let", Arity::AtLeast(1), let_parallel)
+        .special_form("let*", Arity::AtLeast(1), let_sequential)
+        .special_form("letrec", Arity::AtLeast(1), letrec);
+}
+
+fn nil() -> Expr {
+    Expr::Atomic(Atom::Boolean(Bool::Nil))
+}
+
+fn var_name(expr: &Expr) -> Result<&str> {
+    match expr {
+        Expr::Atomic(Atom::Name(name)) => Ok(name),
+        _ => Err(EvalError::InvalidVarName),
+    }
+}
+
+/// `(defvar name [value])`: define a global variable, unless it is already
+/// defined. `value` is not evaluated in that case.
+fn defvar(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    let name = var_name(&args[0])?;
+    if ctx.globals.lookup_var(name).is_none() {
+        if let Some(value) = args.get(1) {
+            let value = ctx.eval_ast(value)?;
+            ctx.globals.define_var(name, value);
+        }
+    }
+    Ok(Expr::Atomic(Atom::Symbol(name.to_string())))
+}
+
+/// `(defparameter name value)`: define a global variable, or set it if it is
+/// already defined
+fn defparameter(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    let name = var_name(&args[0])?;
+    let value = ctx.eval_ast(&args[1])?;
+    ctx.globals.define_var(name, value);
+    Ok(Expr::Atomic(Atom::Symbol(name.to_string())))
+}
+
+/// `(setq name value...)`: set each variable in turn to the value of the
+/// form following it, and return the last value. `(set! name value)` is
+/// the same with a single variable.
+fn setq(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    if !args.len().is_multiple_of(2) {
+        return Err(EvalError::InvalidSyntax);
+    }
+    let mut res = nil();
+    for pair in args.chunks(2) {
+        let name = var_name(&pair[0])?;
+        res = ctx.eval_ast(&pair[1])?;
+        if !ctx.env.assign_var(name, res.clone()) {
+            return Err(EvalError::SetVoidVariable {
+                name: name.to_string(),
+            });
+        }
+    }
+    Ok(res)
+}
+
+/// The bindings of a `let` form: each is `name`, `(name)` or
+/// `(name value)`, where a missing value stands for nil.
+fn bindings(spec: &Expr) -> Result<Vec<(&str, Option<&Expr>)>> {
+    let specs = match spec {
+        Expr::List(specs) => specs.as_slice(),
+        Expr::Atomic(Atom::Boolean(Bool::Nil)) => &[],
+        _ => return Err(EvalError::InvalidSyntax),
+    };
+    specs
+        .iter()
+        .map(|binding| match binding {
+            Expr::List(binding) => match binding.as_slice() {
+                [name] => Ok((var_name(name)?, None)),
+                [name, value] => Ok((var_name(name)?, Some(value))),
+                _ => Err(EvalError::InvalidSyntax),
+            },
+            name => Ok((var_name(name)?, None)),
+        })
+        .collect()
+}
+
+fn eval_or_nil(ctx: &mut Context, value: Option<&Expr>) -> Result<Expr> {
+    value.map_or_else(|| Ok(nil()), |value| ctx.eval_ast(value))
+}
+
+/// `(let (bindings...) body...)`: evaluate all the values first, then the
+/// body with the variables bound to them
+fn let_parallel(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    let frame = Env::child(&ctx.env);
+    for (name, value) in bindings(&args[0])? {
+        frame.define_var(name, eval_or_nil(ctx, value)?);
+    }
+    ctx.with_env(frame, |ctx| ctx.progn(&args[1..]))
+}
+
+/// `(let* (bindings...) body...)`: like `let`, but each value is evaluated
+/// with the variables before it bound
+fn let_sequential(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    let mut env = Rc::clone(&ctx.env);
+    for (name, value) in bindings(&args[0])? {
+        // A frame per variable, so that closures made by a value do not see
+        // the variables after it, even when they shadow earlier ones
+        let value = ctx.with_env(Rc::clone(&env), |ctx| eval_or_nil(ctx, value))?;
+        env = Env::child(&env);
+        env.define_var(name, value);
+    }
+    ctx.with_env(env, |ctx| ctx.progn(&args[1..]))
+}
+
+/// `(letrec (bindings...) body...)`: like `let`, but the values are
+/// evaluated with all the variables bound, so that functions can refer to
+/// each other. The variables are nil until their value is evaluated.
+fn letrec(ctx: &mut Context, args: Vec<Expr>) -> Result<Expr> {
+    let bindings = bindings(&args[0])?;
+    let frame = Env::child(&ctx.env);
+    for (name, _) in &bindings {
+        frame.define_var(name, nil());
+    }
+    ctx.with_env(frame, |ctx| {
+        for (name, value) in bindings {
+            let value = eval_or_nil(ctx, value)?;
+            ctx.env.define_var(name, value);
+        }
+        ctx.progn(&args[1..])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::evaluator::{eval_str, show, Context, EvalError};
+
+    #[test]
+    fn should_define_globals() {
+        let mut ctx = Context::default();
+        assert_eq!(show(&mut ctx, "(defvar x 1)"), "x");
+        assert_eq!(show(&mut ctx, "(defvar x undefined) x"), "1");
+        assert_eq!(show(&mut ctx, "(defparameter x (+ x 1)) x"), "2");
+        assert_eq!(show(&mut ctx, "(defun init () (defvar z 3)) (init) z"), "3");
+        assert!(matches!(
+            eval_str(&mut ctx, "(defvar y) y"),
+            Err(EvalError::VoidVariable { .. })
+        ));
+        assert!(matches!(
+            eval_str(&mut ctx, "(defvar 1 2)"),
+            Err(EvalError::InvalidVarName)
+        ));
+    }
+
+    #[test]
+    fn should_assign_variables() {
+        let mut ctx = Context::default();
+        assert_eq!(
+            show(&mut ctx, "(defvar x 0) (defvar y 0) (setq x 5 y (+ x 1))"),
+            "6"
+        );
+        assert_eq!(show(&mut ctx, "(set! x (list x y)) x"), "(5 6)");
+        assert_eq!(show(&mut ctx, "(setq)"), "nil");
+        assert!(matches!(
+            eval_str(&mut ctx, "(setq undefined 1)"),
+            Err(EvalError::SetVoidVariable { name }) if name == "undefined"
+        ));
+        assert!(matches!(
+            eval_str(&mut ctx, "(setq x 1 y)"),
+            Err(EvalError::InvalidSyntax)
+        ));
+        assert!(matches!(
+            eval_str(&mut ctx, "(setq nil 1)"),
+            Err(EvalError::InvalidVarName)
+        ));
+        // Only the innermost binding changes
+        assert_eq!(show(&mut ctx, "(let ((x 10)) (setq x 11) x)"), "11");
+        assert_eq!(
+            show(&mut ctx, "(defun bump (x) (setq x (+ x 1))) (bump 1)"),
+            "2"
+        );
+        assert_eq!(show(&mut ctx, "x"), "(5 6)");
+    }
+
+    #[test]
+    fn should_share_captured_bindings() {
+        let mut ctx = Context::default();
+        eval_str(
+            &mut ctx,
+            "(defun make-counter () (let ((n 0)) (lambda () (setq n (+ n 1)))))",
+        )
+        .unwrap();
+        assert_eq!(
+            show(
+                &mut ctx,
+                "(defvar a (make-counter)) (defvar b (make-counter))
+                 (list (funcall a) (funcall a) (funcall b) (funcall a))"
+            ),
+            "(1 2 1 3)"
+        );
+        assert_eq!(
+            show(
+                &mut ctx,
+                "(defvar n 0) (defun next () (setq n (+ n 1)) n) (next) (next)"
+            ),
+            "2"
+        );
+    }
+
+    #[test]
+    fn should_bind_locals() {
+        let mut ctx = Context::default();
+        assert_eq!(
+            show(&mut ctx, "(let (a (b) (c 3)) (list a b c))"),
+            "(nil nil 3)"
+        );
+        assert_eq!(show(&mut ctx, "(let () 1 2)"), "2");
+        assert_eq!(show(&mut ctx, "(let ((x 1)))"), "nil");
+        assert!(matches!(
+            eval_str(&mut ctx, "(let ((x 1)) x) x"),
+            Err(EvalError::VoidVariable { .. })
+        ));
+        assert_eq!(
+            show(&mut ctx, "(let ((x 1)) (let ((x 2) (y x)) (list x y)))"),
+            "(2 1)"
+        );
+        assert_eq!(
+            show(&mut ctx, "(let ((x 1)) (let* ((x 2) (y x)) (list x y)))"),
+            "(2 2)"
+        );
+        assert_eq!(
+            show(
+                &mut ctx,
+                "(let* ((x 1) (f (lambda () x)) (x 2)) (list x (funcall f)))"
+            ),
+            "(2 1)"
+        );
+        assert_eq!(
+            show(
+                &mut ctx,
+                "(letrec ((f (lambda () (funcall g))) (g (lambda () 'g))) (funcall f))"
+            ),
+            "g"
+        );
+        assert_eq!(
+            show(&mut ctx, "(letrec ((x y) (y 1)) (list x y))"),
+            "(nil 1)"
+        );
+        assert!(matches!(
+            eval_str(&mut ctx, "(let ((1 2)) 3)"),
+            Err(EvalError::InvalidVarName)
+        ));
+        assert!(matches!(
+            eval_str(&mut ctx, "(let* ((x 1 2)) x)"),
+            Err(EvalError::InvalidSyntax)
+        ));
+    }
+}
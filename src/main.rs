@@ -5,7 +5,7 @@ mod evaluator;
 pub mod parser;
 
 use crate::evaluator::Context;
-use crate::parser::parse_str;
+use crate::parser::{parse_forms, parse_program};
 use rustyline::{Cmd, KeyCode, KeyEvent, Modifiers};
 
 use clap::Parser;
@@ -23,14 +23,26 @@ struct Args {
 }
 
 const PROMPT: &str = "rusp> ";
+const CONTINUATION_PROMPT: &str = "  ... ";
 
 fn main() {
     let args = Args::parse();
+    // Deep recursion needs more stack than the main thread has
+    let interpreter = std::thread::Builder::new()
+        .stack_size(evaluator::STACK_SIZE)
+        .spawn(move || run(args))
+        .expect("Cannot start the interpreter thread");
+    if interpreter.join().is_err() {
+        std::process::exit(101);
+    }
+}
+
+fn run(args: Args) {
     match args.expr {
         Some(expr) => {
             let mut ctx = Context::new(args.debug);
-            for line in expr.lines() {
-                match parse_str(&line) {
+            for form in parse_forms(&expr) {
+                match form {
                     Ok(ast) => {
                         let result = ctx.eval_ast(&ast);
                         match result {
@@ -58,17 +70,11 @@ fn repl(debug: bool) {
     let mut ctx = Context::new(debug);
     let mut rl = Editor::<()>::new();
     rl.bind_sequence(
-        KeyEvent {
-            0: KeyCode::Up,
-            1: Modifiers::NONE,
-        },
+        KeyEvent(KeyCode::Up, Modifiers::NONE),
         Cmd::HistorySearchForward,
     );
     rl.bind_sequence(
-        KeyEvent {
-            0: KeyCode::Down,
-            1: Modifiers::NONE,
-        },
+        KeyEvent(KeyCode::Down, Modifiers::NONE),
         Cmd::HistorySearchBackward,
     );
     rl.bind_sequence(KeyEvent::alt('n'), Cmd::HistorySearchForward);
@@ -77,23 +83,40 @@ fn repl(debug: bool) {
         println!("No previous history.");
     }
 
+    // Lines of a form that is not complete yet
+    let mut buffer = String::new();
     loop {
-        let readline = rl.readline(PROMPT);
+        let prompt = if buffer.is_empty() {
+            PROMPT
+        } else {
+            CONTINUATION_PROMPT
+        };
+        let readline = rl.readline(prompt);
         match readline {
             Ok(line) => {
-                match parse_str(&line) {
-                    Ok(ast) => {
-                        let result = ctx.eval_ast(&ast);
-                        match result {
-                            Ok(result) => print!("{}\r\n", result),
-                            Err(e) => eprint!("Eval error: {}\r\n", e),
+                buffer.push_str(&line);
+                buffer.push('\n');
+                match parse_program(&buffer) {
+                    Err(e) if e.is_incomplete() => continue,
+                    Ok(forms) => {
+                        for ast in forms {
+                            let result = ctx.eval_ast(&ast);
+                            match result {
+                                Ok(result) => print!("{}\r\n", result),
+                                Err(e) => eprint!("Eval error: {}\r\n", e),
+                            }
                         }
                     }
                     Err(e) => eprint!("{}\r\n", e),
                 };
-                rl.add_history_entry(line.as_str());
+                rl.add_history_entry(buffer.trim_end());
+                buffer.clear();
             }
 
+            Err(ReadlineError::Interrupted) if !buffer.is_empty() => {
+                // Abandon the form being entered
+                buffer.clear();
+            }
             Err(ReadlineError::Interrupted) => {
                 println!("CTRL-C");
                 break;
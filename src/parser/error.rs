@@ -0,0 +1,173 @@
+use super::span::{LineIndex, Position};
+use custom_error::custom_error;
+use nom::error::{ErrorKind, FromExternalError, ParseError};
+use std::fmt;
+
+custom_error! {
+    pub SyntaxError
+    TrailingGarbage{pos: Position, excerpt: Excerpt} = "Syntax error at {pos}: trailing garbage following expression\n{excerpt}",
+    UnmatchedParen{pos: Position, excerpt: Excerpt} = "Syntax error at {pos}: unmatched `)`, there is no list to close\n{excerpt}",
+    Unexpected{pos: Position, expected: Expected, excerpt: Excerpt} = "Syntax error at {pos}: expected {expected}\n{excerpt}",
+    Incomplete{pos: Position, expected: Expected, excerpt: Excerpt} = "Syntax error at {pos}: input ends before this form is complete, expected {expected}\n{excerpt}"
+}
+
+impl SyntaxError {
+    /// Whether the input is a valid prefix of a program that only misses
+    /// its end, e.g. an unclosed list or string. Reading more input may fix
+    /// such an error.
+    pub fn is_incomplete(&self) -> bool {
+        matches!(self, SyntaxError::Incomplete { .. })
+    }
+}
+
+/// What the reader was looking for when it failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expected {
+    Expression,
+    ClosingParen,
+    StringTerminator,
+    EscapeSequence,
+    CommentTerminator,
+    Number,
+}
+
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expected::Expression => write!(f, "an expression"),
+            Expected::ClosingParen => write!(f, "`)`"),
+            Expected::StringTerminator => write!(f, "a closing `\"` to end the string"),
+            Expected::EscapeSequence => write!(f, "a valid escape sequence"),
+            Expected::CommentTerminator => write!(f, "a closing `|#` to end the comment"),
+            Expected::Number => write!(f, "a valid number"),
+        }
+    }
+}
+
+/// The source line an error occurred on, rendered with a caret under the
+/// offending character.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Excerpt {
+    line: String,
+    pos: Position,
+}
+
+impl Excerpt {
+    pub(super) fn new(lines: &LineIndex, pos: Position) -> Self {
+        let line = lines.source()[lines.line_start(pos.line)..]
+            .lines()
+            .next()
+            .unwrap_or("")
+            .to_string();
+        Excerpt { line, pos }
+    }
+}
+
+impl fmt::Display for Excerpt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let number = self.pos.line.to_string();
+        let margin = " ".repeat(number.len());
+        let indent: String = self
+            .line
+            .chars()
+            .take(self.pos.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        writeln!(f, "{} | {}", number, self.line)?;
+        write!(f, "{} | {}^", margin, indent)
+    }
+}
+
+/// Error of the reader's parsers. Like spans, its location is stored as the
+/// remaining input and only turned into a position once the whole source is
+/// known.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct ReadError<'a> {
+    pub(super) at: &'a str,
+    pub(super) kind: ReadErrorKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum ReadErrorKind {
+    /// Something else than `Expected` was found at the error location.
+    Expected(Expected),
+    /// The input ended before the form starting at the error location, which
+    /// still needed `Expected`.
+    Incomplete(Expected),
+}
+
+pub(super) type PResult<'a, O> = nom::IResult<&'a str, O, ReadError<'a>>;
+
+impl<'a> ReadError<'a> {
+    pub(super) fn expected(at: &'a str, expected: Expected) -> Self {
+        ReadError {
+            at,
+            kind: ReadErrorKind::Expected(expected),
+        }
+    }
+
+    pub(super) fn incomplete(at: &'a str, expected: Expected) -> Self {
+        ReadError {
+            at,
+            kind: ReadErrorKind::Incomplete(expected),
+        }
+    }
+
+    pub(super) fn into_syntax_error(self, lines: &LineIndex) -> SyntaxError {
+        let pos = lines.position(lines.source().len() - self.at.len());
+        let excerpt = Excerpt::new(lines, pos);
+        match self.kind {
+            // Anything could still follow when the input ran out
+            ReadErrorKind::Expected(expected) if self.at.is_empty() => SyntaxError::Incomplete {
+                pos,
+                expected,
+                excerpt,
+            },
+            ReadErrorKind::Expected(expected) => SyntaxError::Unexpected {
+                pos,
+                expected,
+                excerpt,
+            },
+            ReadErrorKind::Incomplete(expected) => SyntaxError::Incomplete {
+                pos,
+                expected,
+                excerpt,
+            },
+        }
+    }
+}
+
+impl<'a> ParseError<&'a str> for ReadError<'a> {
+    fn from_error_kind(input: &'a str, _kind: ErrorKind) -> Self {
+        ReadError::expected(input, Expected::Expression)
+    }
+
+    fn append(_input: &'a str, _kind: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    /// Among alternatives, report the one that went the furthest.
+    fn or(self, other: Self) -> Self {
+        if other.at.len() < self.at.len() {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+impl<'a, E> FromExternalError<&'a str, E> for ReadError<'a> {
+    fn from_external_error(input: &'a str, kind: ErrorKind, _e: E) -> Self {
+        ReadError::from_error_kind(input, kind)
+    }
+}
+
+/// Turn the error of a parser that ran on `input` into a syntax error.
+/// Streaming parsers only run out of input inside an unterminated form.
+pub(super) fn syntax_error(input: &str, e: nom::Err<ReadError>, lines: &LineIndex) -> SyntaxError {
+    match e {
+        nom::Err::Error(e) | nom::Err::Failure(e) => e.into_syntax_error(lines),
+        nom::Err::Incomplete(_) => ReadError::expected(&input[input.len()..], Expected::Expression)
+            .into_syntax_error(lines),
+    }
+}
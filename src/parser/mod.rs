@@ -1,98 +1,287 @@
+mod error;
+mod number;
+mod span;
 mod strparser;
 mod types;
 
-use custom_error::custom_error;
-use nom::character::complete::{alpha1, alphanumeric1, char, one_of};
-use nom::combinator::{map, map_res, not, recognize};
-use nom::multi::{many0, many1, separated_list0};
-use nom::number::complete::double;
-use nom::sequence::{delimited, pair, preceded, terminated};
-use nom::IResult;
+use nom::bytes::complete::{take_while, take_while1};
+use nom::character::complete::{anychar, char};
+use nom::combinator::{map, not, opt, recognize, verify};
+use nom::multi::{many0, many1};
+use nom::sequence::{delimited, pair, preceded};
 use nom::{
     branch::alt,
     bytes::complete::{tag, tag_no_case},
 };
 
-pub use types::{Atom, Bool, Expr, Num, Ops};
+use error::{syntax_error, PResult, ReadError};
+use span::{LineIndex, RawSpans};
 
-custom_error! {
-    pub SyntaxError
-    TrailingGarbage = "Trailing garbage following expression",
-    ParsingError{message: String} = "Invalid syntax: {message}"
-}
+pub use error::{Excerpt, Expected, SyntaxError};
+pub use span::{Position, Span, SpanTree};
+pub use types::{Atom, Bool, Expr, Num};
 
-fn decimal(input: &str) -> IResult<&str, i64> {
-    map_res(
-        recognize(terminated(
-            many1(terminated(one_of("0123456789"), many0(char('_')))),
-            not(alt((tag("."), tag_no_case("e")))),
-        )),
-        |out: &str| str::replace(out, "_", "").parse::<i64>(),
-    )(input)
+/// Characters that end a symbol or number token.
+fn is_delimiter(c: char) -> bool {
+    c.is_whitespace() || "()\";'`,".contains(c)
 }
 
-fn identifier(input: &str) -> IResult<&str, &str> {
+/// A maximal run of non-delimiter characters, e.g. `string->list`, `+` or
+/// `12.5`. `#` is kept for reader syntax and can only start radix numbers
+/// such as `#xFF`.
+fn token(input: &str) -> PResult<'_, &str> {
     recognize(pair(
-        alt((alpha1, tag("_"))),
-        many0(alt((alphanumeric1, tag("_")))),
+        alt((
+            tag_no_case("#x"),
+            tag_no_case("#o"),
+            tag_no_case("#b"),
+            recognize(verify(anychar, |c| !is_delimiter(*c) && *c != '#')),
+        )),
+        take_while(|c| !is_delimiter(c)),
     ))(input)
 }
 
-fn operator(input: &str) -> IResult<&str, Ops> {
-    alt((
-        map(tag("add"), |_| Ops::Add),
-        map(tag("sub"), |_| Ops::Sub),
-        map(tag("mul"), |_| Ops::Mul),
-        map(tag("div"), |_| Ops::Div),
-        map(tag("+"), |_| Ops::Add),
-        map(tag("-"), |_| Ops::Sub),
-        map(tag("*"), |_| Ops::Mul),
-        map(tag("/"), |_| Ops::Div),
-        map(tag("%"), |_| Ops::Rem),
-        map(tag("nth"), |_| Ops::Nth),
-        map(tag("list"), |_| Ops::List),
-        map(tag("eval"), |_| Ops::Eval),
-        map(tag("car"), |_| Ops::Car),
-        map(tag("defun"), |_| Ops::Defun),
-        // map(tag("map"), |_| Ops::Map),
-    ))(input)
+/// A number, a boolean or a symbol. The whole token is read first and only then
+/// resolved, so that a number never matches a prefix of a longer name. A token
+/// that looks like a number but is not one is a name, as in `1+` or `2nd`,
+/// except after `#` which only starts radix numbers. A number with no valid
+/// value, like `1e99999`, is an error.
+fn atom(input: &str) -> PResult<'_, Atom> {
+    let (rest, token) = token(input)?;
+    let invalid = || nom::Err::Failure(ReadError::expected(input, Expected::Number));
+    let atom = match number::is_numeric(token).then(|| number::parse(token)) {
+        Some(Ok(Some(num))) => Atom::Number(num),
+        Some(Err(_)) => return Err(invalid()),
+        Some(Ok(None)) if token.starts_with('#') => return Err(invalid()),
+        _ => match token {
+            "t" => Atom::Boolean(Bool::True),
+            "nil" => Atom::Boolean(Bool::Nil),
+            _ => Atom::Name(token.to_string()),
+        },
+    };
+    Ok((rest, atom))
+}
+
+/// A string literal. The string parser is a streaming one, so running out of
+/// input means the closing quote is missing.
+fn string(input: &str) -> PResult<'_, String> {
+    match strparser::parse_string::<ReadError>(input) {
+        Err(nom::Err::Incomplete(_)) => Err(nom::Err::Failure(ReadError::incomplete(
+            input,
+            Expected::StringTerminator,
+        ))),
+        Err(nom::Err::Error(e)) if input.starts_with('"') => Err(nom::Err::Failure(
+            ReadError::expected(e.at, Expected::EscapeSequence),
+        )),
+        res => res,
+    }
+}
+
+/// A `;` comment running until the end of the line.
+fn line_comment(input: &str) -> PResult<'_, &str> {
+    recognize(pair(char(';'), take_while(|c| c != '\n')))(input)
+}
+
+/// A `#| ... |#` block comment. Block comments nest, so `#| a #| b |# c |#`
+/// is a single comment.
+fn block_comment(input: &str) -> PResult<'_, &str> {
+    let (body, _) = tag("#|")(input)?;
+    let (rest, _) = many0(alt((
+        block_comment,
+        recognize(pair(not(alt((tag("#|"), tag("|#")))), anychar)),
+    )))(body)?;
+    match tag::<_, _, ReadError>("|#")(rest) {
+        Ok((rest, _)) => Ok((rest, &input[..input.len() - rest.len()])),
+        Err(_) => Err(nom::Err::Failure(ReadError::incomplete(
+            input,
+            Expected::CommentTerminator,
+        ))),
+    }
+}
+
+/// A `#;` datum comment, which comments out the expression that follows it.
+fn datum_comment(input: &str) -> PResult<'_, &str> {
+    recognize(pair(tag("#;"), pair(space0, expression)))(input)
+}
+
+/// A non-empty run of whitespace or comments, used to separate forms. Any
+/// Unicode whitespace (spaces, tabs, newlines, ...) is accepted.
+fn space1(input: &str) -> PResult<'_, &str> {
+    recognize(many1(alt((
+        take_while1(char::is_whitespace),
+        line_comment,
+        block_comment,
+        datum_comment,
+    ))))(input)
+}
+
+/// Any run of whitespace or comments, possibly empty.
+fn space0(input: &str) -> PResult<'_, &str> {
+    map(opt(space1), |s| s.unwrap_or(""))(input)
+}
+
+/// An expression along with the spans of itself and its sub-expressions.
+type Spanned = (Expr, RawSpans);
+
+/// A parenthesised list. Once the opening paren is read, failing to find the
+/// matching closing one is an error that points back to the opener.
+fn spanned_sexpr(input: &str) -> PResult<'_, Vec<Spanned>> {
+    let (mut rest, _) = char('(')(input)?;
+    let mut nodes = vec![];
+    loop {
+        rest = space0(rest)?.0;
+        if let Ok((rest, _)) = char::<_, ReadError>(')')(rest) {
+            return Ok((rest, nodes));
+        }
+        if rest.is_empty() {
+            return Err(nom::Err::Failure(ReadError::incomplete(
+                input,
+                Expected::ClosingParen,
+            )));
+        }
+        match spanned_expression(rest) {
+            Ok((next, node)) => {
+                nodes.push(node);
+                rest = next;
+            }
+            // Nothing that could start an expression
+            Err(nom::Err::Error(e)) if e.at.len() == rest.len() => {
+                return Err(nom::Err::Failure(ReadError::expected(
+                    rest,
+                    Expected::ClosingParen,
+                )))
+            }
+            Err(nom::Err::Error(e)) => return Err(nom::Err::Failure(e)),
+            Err(e) => return Err(e),
+        }
+    }
 }
 
-fn sexpr(input: &str) -> IResult<&str, Vec<Expr>> {
-    delimited(
-        preceded(tag("("), many0(tag(" "))),
-        separated_list0(many1(tag(" ")), expression),
-        terminated(many0(tag(" ")), tag(")")),
-    )(input)
+#[cfg(test)]
+fn sexpr(input: &str) -> PResult<'_, Vec<Expr>> {
+    map(spanned_sexpr, |nodes| {
+        nodes.into_iter().map(|n| n.0).collect()
+    })(input)
 }
 
-pub fn expression(input: &str) -> IResult<&str, Expr> {
+fn spanned_expression(input: &str) -> PResult<'_, Spanned> {
     use Expr::*;
-    alt((
-        map(operator, |op: Ops| Atomic(Atom::Op(op))),
-        map(strparser::parse_string, |x: String| Atomic(Atom::Quoted(x))),
-        map(identifier, |id: &str| Atomic(Atom::Name(id.to_string()))),
-        map(decimal, |digit: i64| Atomic(Atom::Number(Num::Int(digit)))),
-        map(double, |digit: f64| {
-            Atomic(Atom::Number(Num::Double(digit)))
+    let atomic = |atom| (Atomic(atom), vec![]);
+    let (rest, (expr, children)) = alt((
+        map(string, |x: String| atomic(Atom::Quoted(x))),
+        map(atom, atomic),
+        map(preceded(tag("'"), spanned_expression), |(expr, spans)| {
+            (Qexpr(Box::new(expr)), vec![spans])
         }),
-        map(preceded(tag("'"), sexpr), Qexpr),
-        map(sexpr, List),
-    ))(input)
+        map(preceded(tag("`"), spanned_expression), |(expr, spans)| {
+            (Quasiquote(Box::new(expr)), vec![spans])
+        }),
+        map(preceded(tag(",@"), spanned_expression), |(expr, spans)| {
+            (UnquoteSplicing(Box::new(expr)), vec![spans])
+        }),
+        map(preceded(tag(","), spanned_expression), |(expr, spans)| {
+            (Unquote(Box::new(expr)), vec![spans])
+        }),
+        map(spanned_sexpr, |nodes| {
+            let (exprs, spans) = nodes.into_iter().unzip();
+            (List(exprs), spans)
+        }),
+    ))(input)?;
+    let spans = RawSpans {
+        start: input.len(),
+        end: rest.len(),
+        children,
+    };
+    Ok((rest, (expr, spans)))
+}
+
+fn expression(input: &str) -> PResult<'_, Expr> {
+    map(spanned_expression, |(expr, _)| expr)(input)
+}
+
+/// The error for a `)` at top level, where `at` starts, if there is one.
+fn unmatched_paren(at: &str, lines: &LineIndex) -> Option<SyntaxError> {
+    let at = space0(at).ok()?.0;
+    if !at.starts_with(')') {
+        return None;
+    }
+    let pos = lines.position(lines.source().len() - at.len());
+    Some(SyntaxError::UnmatchedParen {
+        pos,
+        excerpt: Excerpt::new(lines, pos),
+    })
 }
 
 pub fn parse_str(buf_str: &str) -> Result<Expr, SyntaxError> {
-    expression(buf_str)
-        .map_err(|e: nom::Err<_>| SyntaxError::ParsingError {
-            message: e.to_string(),
+    let lines = LineIndex::new(buf_str);
+    let (rest, exp) = delimited(space0, expression, space0)(buf_str).map_err(|e| {
+        unmatched_paren(buf_str, &lines).unwrap_or_else(|| syntax_error(buf_str, e, &lines))
+    })?;
+    if rest.is_empty() {
+        Ok(exp)
+    } else if let Some(err) = unmatched_paren(rest, &lines) {
+        Err(err)
+    } else {
+        let pos = lines.position(buf_str.len() - rest.len());
+        Err(SyntaxError::TrailingGarbage {
+            pos,
+            excerpt: Excerpt::new(&lines, pos),
         })
-        .map(|(r, exp)| {
-            if r.is_empty() {
-                Ok(exp)
-            } else {
-                Err(SyntaxError::TrailingGarbage)
+    }
+}
+
+/// Iterator over the top-level forms of a source text, see [`parse_forms`].
+pub struct Forms<'a> {
+    rest: &'a str,
+    lines: LineIndex<'a>,
+}
+
+impl<'a> Forms<'a> {
+    /// Read the next form along with its spans in the source text.
+    pub fn next_spanned(&mut self) -> Option<Result<(Expr, SpanTree), SyntaxError>> {
+        match preceded(space0, spanned_expression)(self.rest) {
+            Ok((rest, (exp, spans))) => {
+                self.rest = rest;
+                Some(Ok((exp, spans.resolve(&self.lines))))
+            }
+            Err(_) if matches!(space0(self.rest), Ok(("", _))) => None,
+            Err(e) => {
+                let err = unmatched_paren(self.rest, &self.lines)
+                    .unwrap_or_else(|| syntax_error(self.rest, e, &self.lines));
+                self.rest = "";
+                Some(Err(err))
             }
-        })?
+        }
+    }
+}
+
+impl<'a> Iterator for Forms<'a> {
+    type Item = Result<Expr, SyntaxError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_spanned().map(|res| res.map(|(exp, _)| exp))
+    }
+}
+
+/// Lazily read the top-level forms of `input` one after the other. Iteration
+/// stops after the first syntax error.
+pub fn parse_forms(input: &str) -> Forms<'_> {
+    Forms {
+        rest: input,
+        lines: LineIndex::new(input),
+    }
+}
+
+/// Read every top-level form of `input`, e.g. the content of a source file.
+pub fn parse_program(input: &str) -> Result<Vec<Expr>, SyntaxError> {
+    parse_forms(input).collect()
+}
+
+/// Like [`parse_program`], but also return where each form and its
+/// sub-expressions are located in `input`.
+pub fn parse_program_spanned(input: &str) -> Result<Vec<(Expr, SpanTree)>, SyntaxError> {
+    let mut forms = parse_forms(input);
+    std::iter::from_fn(|| forms.next_spanned()).collect()
 }
 
 #[cfg(test)]
@@ -101,26 +290,25 @@ mod tests {
 
     #[test]
     fn should_parse_numbers_polish() {
-        use Atom::{Number, Op};
+        use Atom::{Name, Number};
         use Expr::Atomic;
         use Num::{Double, Int};
-        use Ops::{Add, Div, Sub};
         let inp = "(+ 3 12 2 (- 2. (/ 4 5E-3)))";
         let res = sexpr(inp).expect("Parsing error");
         assert_eq!(
             res.1,
             [
-                Atomic(Op(Add)),
+                Atomic(Name("+".to_string())),
                 Atomic(Number(Int(3))),
                 Atomic(Number(Int(12))),
                 Atomic(Number(Int(2))),
                 Expr::List(
                     [
-                        Atomic(Op(Sub)),
+                        Atomic(Name("-".to_string())),
                         Atomic(Number(Double(2.0))),
                         Expr::List(
                             [
-                                Atomic(Op(Div)),
+                                Atomic(Name("/".to_string())),
                                 Atomic(Number(Int(4))),
                                 Atomic(Number(Double(0.005)))
                             ]
@@ -150,6 +338,238 @@ mod tests {
         )
     }
 
+    #[test]
+    fn should_parse_any_whitespace() {
+        use Atom::{Name, Number};
+        use Expr::Atomic;
+        use Num::Int;
+        let inp = "(\tlexp 3\n  2\r\n\u{3000}(lexp)\n)";
+        let res = sexpr(inp).expect("Parsing error");
+        assert_eq!(
+            res.1,
+            [
+                Atomic(Name("lexp".to_string())),
+                Atomic(Number(Int(3))),
+                Atomic(Number(Int(2))),
+                Expr::List([Atomic(Name("lexp".to_string()))].to_vec())
+            ]
+        );
+        let res = parse_str("\n  (lexp\n 3)\n").expect("Parsing error");
+        assert_eq!(
+            res,
+            Expr::List([Atomic(Name("lexp".to_string())), Atomic(Number(Int(3)))].to_vec())
+        );
+    }
+
+    #[test]
+    fn should_skip_comments() {
+        use Atom::{Name, Number, Quoted};
+        use Expr::Atomic;
+        use Num::Int;
+        let inp = "(lexp ; first comment\n 3 #| block #| nested |# |#2\n #;(ignored 1) \"; not a comment\"#;4)";
+        let res = sexpr(inp).expect("Parsing error");
+        assert_eq!(
+            res.1,
+            [
+                Atomic(Name("lexp".to_string())),
+                Atomic(Number(Int(3))),
+                Atomic(Number(Int(2))),
+                Atomic(Quoted("; not a comment".to_string()))
+            ]
+        );
+        let res =
+            parse_str("; leading comment\n#| block |# #; 12 32 ; trailing").expect("Parsing error");
+        assert_eq!(res, Atomic(Number(Int(32))));
+        assert!(parse_str("#| unterminated #| nested |# 32").is_err());
+    }
+
+    #[test]
+    fn should_parse_program() {
+        use Atom::{Name, Number};
+        use Expr::Atomic;
+        use Num::Int;
+        let inp = "(def f (x)\n  (lexp x)) ; define f\n\n(f 3)\n32";
+        let res = parse_program(inp).expect("Parsing error");
+        assert_eq!(
+            res,
+            [
+                Expr::List(
+                    [
+                        Atomic(Name("def".to_string())),
+                        Atomic(Name("f".to_string())),
+                        Expr::List([Atomic(Name("x".to_string()))].to_vec()),
+                        Expr::List(
+                            [
+                                Atomic(Name("lexp".to_string())),
+                                Atomic(Name("x".to_string()))
+                            ]
+                            .to_vec()
+                        )
+                    ]
+                    .to_vec()
+                ),
+                Expr::List([Atomic(Name("f".to_string())), Atomic(Number(Int(3)))].to_vec()),
+                Atomic(Number(Int(32)))
+            ]
+        );
+        assert_eq!(parse_program(" ; nothing here\n").unwrap(), []);
+        let mut forms = parse_forms("(f 3) (f 4))");
+        assert!(forms.next().unwrap().is_ok());
+        assert!(forms.next().unwrap().is_ok());
+        assert!(forms.next().unwrap().is_err());
+        assert!(forms.next().is_none());
+    }
+
+    #[test]
+    fn should_locate_forms() {
+        let inp = "32 ; first\n(lexp\n  '(\"é\" x))";
+        let res = parse_program_spanned(inp).expect("Parsing error");
+        assert_eq!(res.len(), 2);
+        let pos = |offset, line, column| Position {
+            offset,
+            line,
+            column,
+        };
+        assert_eq!(res[0].1.span.start, pos(0, 1, 1));
+        assert_eq!(res[0].1.span.end, pos(2, 1, 3));
+        assert!(res[0].1.children.is_empty());
+        let list = &res[1].1;
+        assert_eq!(list.span.start, pos(11, 2, 1));
+        assert_eq!(list.span.end, pos(inp.len(), 3, 12));
+        assert_eq!(list.children.len(), 2);
+        let quoted = &list.children[1];
+        assert_eq!(quoted.span.start, pos(19, 3, 3));
+        assert_eq!(quoted.children[0].span.start, pos(20, 3, 4));
+        let items = &quoted.children[0].children;
+        assert_eq!(items[0].span.end, pos(25, 3, 8));
+        assert_eq!(items[1].span.start, pos(26, 3, 9));
+        // Spans are kept aside, expressions compare as before
+        assert_eq!(res[1].0, parse_str(&inp[11..]).unwrap());
+    }
+
+    #[test]
+    fn should_report_syntax_errors() {
+        let at = |res: Result<Vec<Expr>, SyntaxError>| match res {
+            Err(SyntaxError::Unexpected { pos, expected, .. }) => (pos.line, pos.column, expected),
+            res => panic!("Unexpected result {:?}", res),
+        };
+        assert_eq!(
+            at(parse_program("(lexp\n  (x 1) #z")),
+            (2, 9, Expected::ClosingParen)
+        );
+        assert_eq!(
+            at(parse_program("(lexp \"a\\qc\")")),
+            (1, 9, Expected::EscapeSequence)
+        );
+        assert_eq!(at(parse_program("(lexp #x12z)")), (1, 7, Expected::Number));
+        for inp in ["(lexp 1))", " )"] {
+            match parse_program(inp) {
+                Err(err @ SyntaxError::UnmatchedParen { .. }) => assert!(err
+                    .to_string()
+                    .starts_with(&format!("Syntax error at 1:{}: unmatched `)`", inp.len()))),
+                res => panic!("Unexpected result {:?}", res),
+            }
+        }
+        assert!(matches!(
+            parse_str("(lexp 1) )"),
+            Err(SyntaxError::UnmatchedParen { .. })
+        ));
+        match parse_program("(lexp 1)\n  (lexp (x 1)") {
+            Err(SyntaxError::Incomplete {
+                pos,
+                expected,
+                excerpt,
+            }) => {
+                assert_eq!((pos.line, pos.column), (2, 3));
+                assert_eq!(expected, Expected::ClosingParen);
+                assert_eq!(excerpt.to_string(), "2 |   (lexp (x 1)\n  |   ^");
+            }
+            res => panic!("Unexpected result {:?}", res),
+        }
+        match parse_str("(lexp 1) 2") {
+            Err(SyntaxError::TrailingGarbage { pos, .. }) => assert_eq!(pos.column, 10),
+            res => panic!("Unexpected result {:?}", res),
+        }
+        // Columns count characters, the excerpt starts at the line's first byte
+        match parse_program("(lexp 1)\n(f \"é\" éé #z)") {
+            Err(SyntaxError::Unexpected { pos, excerpt, .. }) => {
+                assert_eq!((pos.line, pos.column), (2, 11));
+                assert_eq!(excerpt.to_string(), "2 | (f \"é\" éé #z)\n  |           ^");
+            }
+            res => panic!("Unexpected result {:?}", res),
+        }
+        match parse_str("(éé) 2") {
+            Err(SyntaxError::TrailingGarbage { pos, excerpt }) => {
+                assert_eq!(pos.column, 6);
+                assert_eq!(excerpt.to_string(), "1 | (éé) 2\n  |      ^");
+            }
+            res => panic!("Unexpected result {:?}", res),
+        }
+    }
+
+    #[test]
+    fn should_tell_incomplete_from_invalid() {
+        let incomplete = |inp| parse_program(inp).unwrap_err().is_incomplete();
+        assert!(incomplete("(defun f (x)"));
+        assert!(incomplete("(lexp \"abc)"));
+        assert!(incomplete("(lexp 1) #| a #| b |# c"));
+        assert!(incomplete("(lexp '"));
+        assert!(incomplete("(lexp #;"));
+        assert!(!incomplete("(lexp 1))"));
+        assert!(!incomplete("(lexp 1 #z"));
+        assert!(incomplete("(lexp 12abc"));
+        assert!(!incomplete("(lexp #b12"));
+    }
+
+    #[test]
+    fn should_lex_whole_symbols() {
+        use Atom::{Name, Number};
+        use Expr::Atomic;
+        use Num::Int;
+        let inp = "(listp address string->list empty? my-var set! *x* <= - car 1_000 1+ 2nd 1.2.3)";
+        let res = sexpr(inp).expect("Parsing error");
+        let name = |n: &str| Atomic(Name(n.to_string()));
+        assert_eq!(
+            res.1,
+            [
+                name("listp"),
+                name("address"),
+                name("string->list"),
+                name("empty?"),
+                name("my-var"),
+                name("set!"),
+                name("*x*"),
+                name("<="),
+                Atomic(Name("-".to_string())),
+                Atomic(Name("car".to_string())),
+                Atomic(Number(Int(1000))),
+                name("1+"),
+                name("2nd"),
+                name("1.2.3")
+            ]
+        );
+        assert_eq!(
+            sexpr("(f(g)x)").expect("Parsing error").1,
+            [name("f"), Expr::List([name("g")].to_vec()), name("x")]
+        );
+    }
+
+    #[test]
+    fn should_parse_booleans() {
+        use Atom::{Boolean, Name};
+        use Expr::Atomic;
+        let res = sexpr("(t nil tea nil?)").expect("Parsing error");
+        assert_eq!(
+            res.1,
+            [
+                Atomic(Boolean(Bool::True)),
+                Atomic(Boolean(Bool::Nil)),
+                Atomic(Name("tea".to_string())),
+                Atomic(Name("nil?".to_string()))
+            ]
+        );
+    }
+
     #[test]
     fn should_parse_quoted_strings() {
         use Atom::{Name, Number, Quoted};
@@ -170,10 +590,9 @@ mod tests {
 
     #[test]
     fn should_parse_qexpr() {
-        use Atom::{Name, Number, Op, Quoted};
+        use Atom::{Name, Number, Quoted};
         use Expr::{Atomic, Qexpr};
         use Num::Int;
-        use Ops;
         let inp = "(lexp 3  2 '(3 (+ 2 5)  \"ok\" )  )";
         let res = expression(inp).expect("Parsing error");
         assert_eq!(
@@ -183,12 +602,12 @@ mod tests {
                     Atomic(Name("lexp".to_string())),
                     Atomic(Number(Int(3))),
                     Atomic(Number(Int(2))),
-                    Qexpr(
+                    Qexpr(Box::new(Expr::List(
                         [
                             Atomic(Number(Int(3))),
                             Expr::List(
                                 [
-                                    Atomic(Op(Ops::Add)),
+                                    Atomic(Name("+".to_string())),
                                     Atomic(Number(Int(2))),
                                     Atomic(Number(Int(5))),
                                 ]
@@ -197,13 +616,148 @@ mod tests {
                             Atomic(Quoted("ok".to_string()))
                         ]
                         .to_vec()
-                    )
+                    )))
                 ]
                 .to_vec()
             )
         )
     }
 
+    #[test]
+    fn should_parse_quoted_atoms() {
+        use Atom::{Name, Number};
+        use Expr::{Atomic, Qexpr};
+        let res = sexpr("('foo '42 ''x)").expect("Parsing error");
+        assert_eq!(
+            res.1,
+            [
+                Qexpr(Box::new(Atomic(Name("foo".to_string())))),
+                Qexpr(Box::new(Atomic(Number(Num::Int(42))))),
+                Qexpr(Box::new(Qexpr(Box::new(Atomic(Name("x".to_string()))))))
+            ]
+        );
+    }
+
+    #[test]
+    fn should_parse_quasiquotes() {
+        use Atom::Name;
+        use Expr::{Atomic, Quasiquote, Unquote, UnquoteSplicing};
+        let name = |n: &str| Atomic(Name(n.to_string()));
+        let res = expression("`(a ,x ,@(f rest) `(b ,,y))").expect("Parsing error");
+        assert_eq!(
+            res.1,
+            Quasiquote(Box::new(Expr::List(
+                [
+                    name("a"),
+                    Unquote(Box::new(name("x"))),
+                    UnquoteSplicing(Box::new(Expr::List([name("f"), name("rest")].to_vec()))),
+                    Quasiquote(Box::new(Expr::List(
+                        [name("b"), Unquote(Box::new(Unquote(Box::new(name("y")))))].to_vec()
+                    )))
+                ]
+                .to_vec()
+            )))
+        );
+        assert_eq!(res.1.to_string(), "`(a ,x ,@(f rest) `(b ,,y))");
+    }
+
+    #[test]
+    fn should_parse_number_literals() {
+        use Num::{Double, Int};
+        let num = |inp: &str| match parse_str(inp) {
+            Ok(Expr::Atomic(Atom::Number(n))) => n,
+            res => panic!("Unexpected result {:?}", res),
+        };
+        assert_eq!(num("-3"), Int(-3));
+        assert_eq!(num("+3"), Int(3));
+        assert_eq!(num("-2.5"), Double(-2.5));
+        assert_eq!(num("-.5"), Double(-0.5));
+        assert_eq!(num("2."), Double(2.0));
+        assert_eq!(num("#xFF"), Int(255));
+        assert_eq!(num("#o-17"), Int(-15));
+        assert_eq!(num("#b1010_1010"), Int(170));
+        assert_eq!(num("15e3"), Int(15000));
+        assert_eq!(num("15E-1"), Double(1.5));
+        assert_eq!(num("1_000.000_5"), Double(1000.0005));
+        assert_eq!(num("1_000e1_0"), Int(10_000_000_000_000));
+        let big = |s: &str| Num::Big(s.parse().unwrap());
+        assert_eq!(num("9223372036854775808"), big("9223372036854775808"));
+        assert_eq!(num("-9223372036854775808"), Int(i64::MIN));
+        assert_eq!(
+            num("#x-1_0000_0000_0000_0000"),
+            big("-18446744073709551616")
+        );
+        assert_eq!(num("12e20"), big("1200000000000000000000"));
+        assert_eq!(num("1e1000"), big(&format!("1{}", "0".repeat(1000))));
+        assert_eq!(num("1.0e99999"), Double(f64::INFINITY));
+        for s in [
+            "1e1001",
+            "-1e99999",
+            "(+ 1e99999)",
+            "1/0",
+            "-1/0_0",
+            "0.00000000000000000000000000001m",
+            "99999999999999999999999999999m",
+        ] {
+            assert!(matches!(
+                parse_str(s),
+                Err(SyntaxError::Unexpected {
+                    expected: Expected::Number,
+                    ..
+                })
+            ));
+        }
+        assert_eq!(num("-2/4").to_string(), "-1/2");
+        assert_eq!(num("4/2"), Int(2));
+        assert_eq!(num("1_0/3"), num("10/3"));
+        let complex = |re, im| Num::Complex(num_complex::Complex64::new(re, im));
+        assert_eq!(num("3+4i"), complex(3., 4.));
+        assert_eq!(num("-1.5e1-i"), complex(-15., -1.));
+        assert_eq!(num("1/2+1/4i"), complex(0.5, 0.25));
+        assert_eq!(num("4i"), complex(0., 4.));
+        assert_eq!(num("-2.5i"), complex(0., -2.5));
+        assert_eq!(num("+1i"), complex(0., 1.));
+        assert_eq!(num("inf-infi"), complex(f64::INFINITY, f64::NEG_INFINITY));
+        let dec = |s: &str| Num::Decimal(s.parse().unwrap());
+        assert_eq!(num("12.50m"), dec("12.50"));
+        assert_eq!(num("12.50m").to_string(), "12.50m");
+        assert_eq!(num("-3M"), dec("-3"));
+        assert_eq!(num(".5m"), dec("0.5"));
+        assert_eq!(num("1_000.m"), dec("1000"));
+        for s in ["+i", "-i", "i", "inf-x", "1e3m", "1/2m"] {
+            assert!(!matches!(parse_str(s), Ok(Expr::Atomic(Atom::Number(_)))));
+        }
+        assert_eq!(num("inf"), Double(f64::INFINITY));
+        assert_eq!(num("-inf"), Double(f64::NEG_INFINITY));
+        assert!(matches!(num("nan"), Double(d) if d.is_nan()));
+        for s in [
+            "-", "+", "-x", "info", "#x", "1.2.3", "#xG", "3-", "1/-2", "1/", "1.5/2",
+        ] {
+            assert!(!matches!(parse_str(s), Ok(Expr::Atomic(Atom::Number(_)))));
+        }
+        assert!(matches!(
+            parse_str("(- 1 2)").unwrap(),
+            Expr::List(items) if items[0] == Expr::Atomic(Atom::Name("-".to_string()))
+        ));
+        for n in [
+            Int(-42),
+            big("-123456789012345678901234567890"),
+            num("-5/12"),
+            complex(1., -0.5),
+            complex(-0., 2e100),
+            complex(f64::INFINITY, 1.),
+            dec("-0.010"),
+            Double(3.0),
+            Double(-0.25),
+            Double(1e300),
+            Double(1.5e-10),
+            Double(f64::INFINITY),
+            Double(f64::NEG_INFINITY),
+        ] {
+            assert_eq!(num(&n.to_string()), n);
+        }
+    }
+
     #[test]
     fn should_parse_singles() {
         use Atom::{Name, Number, Quoted};
@@ -0,0 +1,236 @@
+use super::types::Num;
+use nom::branch::alt;
+use nom::bytes::complete::tag_no_case;
+use nom::character::complete::{char, one_of, satisfy};
+use nom::combinator::{all_consuming, map, opt, peek, recognize, value};
+use nom::multi::many0;
+use nom::sequence::{pair, preceded, terminated, tuple};
+use nom::IResult;
+use num_bigint::BigInt;
+use num_complex::Complex64;
+use num_rational::BigRational;
+use num_traits::Zero;
+use rust_decimal::Decimal;
+
+// Numeric literals are read from a whole token, see `parser::atom`:
+//
+//   number   := sign? (digits ('.' digits?)? | '.' digits) exponent?
+//             | sign? digits '/' digits
+//             | sign? (digits ('.' digits?)? | '.' digits) 'm'
+//             | sign? ("inf" | "nan")
+//             | '#' ('x' | 'o' | 'b') sign? digits
+//             | real? ('+' | '-') real? 'i' | real 'i'
+//   exponent := ('e' | 'E') sign? digits
+//
+// where `real` is any of the decimal, ratio or inf/nan forms. A complex
+// number like `3+4i` or `-2.5i` has float parts, and `+i` alone is a symbol
+// while `+1i` is the imaginary unit.
+//
+// A ratio is read in lowest terms, and as an integer if it is one, e.g. `4/2`
+// reads as `2`. A decimal like `12.50m` is a fixed-point number which keeps
+// the scale it is written with, up to 28 digits. Digits can be grouped with
+// `_`, as in `1_000_000` or `3.141_592`.
+
+/// Whether a token looks like a number. It is read as a symbol if it does
+/// not parse as one, see `parser::atom`.
+pub(super) fn is_numeric(token: &str) -> bool {
+    let unsigned = token.strip_prefix(['+', '-']).unwrap_or(token);
+    let mut chars = unsigned.chars();
+    match chars.next() {
+        Some('.') => chars.next().is_some_and(|c| c.is_ascii_digit()),
+        Some(c) => {
+            c.is_ascii_digit()
+                || (c == '#' && unsigned.len() == token.len())
+                || ["inf", "nan"].iter().any(|s| {
+                    // Possibly the real part of a complex, as in `inf+nani`
+                    unsigned.get(..3).is_some_and(|p| p.eq_ignore_ascii_case(s))
+                        && matches!(unsigned[3..].chars().next(), None | Some('+' | '-'))
+                })
+        }
+        None => false,
+    }
+}
+
+/// A token with the syntax of a number but no valid value, like `1e99999`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct InvalidNumber;
+
+/// Parse a numeric token, `None` if it does not have the syntax of a number.
+pub(super) fn parse(token: &str) -> Result<Option<Num>, InvalidNumber> {
+    match all_consuming(alt((radix, complex, special, ratio, fixed, decimal)))(token) {
+        Ok((_, num)) => Ok(Some(num)),
+        Err(nom::Err::Failure(_)) => Err(InvalidNumber),
+        Err(_) => Ok(None),
+    }
+}
+
+/// The error for a literal with the syntax of a number but no valid value.
+/// It is only final when the literal is the whole rest of the token, a part
+/// of a complex number may still be read another way.
+fn invalid<'a>(input: &'a str, rest: &str) -> nom::Err<nom::error::Error<&'a str>> {
+    let e = nom::error::Error::new(input, nom::error::ErrorKind::Verify);
+    if rest.is_empty() {
+        nom::Err::Failure(e)
+    } else {
+        nom::Err::Error(e)
+    }
+}
+
+/// An optional sign, `true` if negative.
+fn sign(input: &str) -> IResult<&str, bool> {
+    map(opt(one_of("+-")), |s| s == Some('-'))(input)
+}
+
+/// Digits in the given radix, possibly grouped with `_`. The underscores
+/// are removed from the result.
+fn digits(radix: u32) -> impl Fn(&str) -> IResult<&str, String> {
+    move |input| {
+        let digit = move |c: char| c.is_digit(radix);
+        map(
+            recognize(pair(
+                satisfy(digit),
+                many0(alt((satisfy(digit), char('_')))),
+            )),
+            |s: &str| s.replace('_', ""),
+        )(input)
+    }
+}
+
+/// `#xFF`, `#o17` or `#b-101`.
+fn radix(input: &str) -> IResult<&str, Num> {
+    let (rest, base) = preceded(
+        char('#'),
+        alt((
+            value(16, one_of("xX")),
+            value(8, one_of("oO")),
+            value(2, one_of("bB")),
+        )),
+    )(input)?;
+    let (rest, (negative, digits)) = pair(sign, digits(base))(rest)?;
+    match BigInt::parse_bytes(digits.as_bytes(), base) {
+        Some(i) if negative => Ok((rest, Num::from_big(-i))),
+        Some(i) => Ok((rest, Num::from_big(i))),
+        None => Err(nom::Err::Error(nom::error::Error::new(
+            input,
+            nom::error::ErrorKind::Digit,
+        ))),
+    }
+}
+
+/// `3+4i`, `1/2-i`, `inf+nani` or `4i`.
+fn complex(input: &str) -> IResult<&str, Num> {
+    let (rest, (re, im)) = alt((
+        pair(real, preceded(peek(one_of("+-")), imaginary)),
+        map(imaginary, |im| (0., im)),
+    ))(input)?;
+    Ok((rest, Num::Complex(Complex64::new(re, im))))
+}
+
+/// A real number as a float, the part of a complex number.
+fn real(input: &str) -> IResult<&str, f64> {
+    map(alt((ratio, decimal, special)), |num| num.to_f64())(input)
+}
+
+/// The imaginary part of a complex number, `+i` and `-i` standing for `+1i`
+/// and `-1i`.
+fn imaginary(input: &str) -> IResult<&str, f64> {
+    terminated(
+        alt((real, map(one_of("+-"), |s| if s == '-' { -1. } else { 1. }))),
+        char('i'),
+    )(input)
+}
+
+/// `1/3` or `-22/7`, with a non-zero denominator.
+fn ratio(input: &str) -> IResult<&str, Num> {
+    let (rest, (negative, numer, denom)) =
+        tuple((sign, digits(10), preceded(char('/'), digits(10))))(input)?;
+    let numer: BigInt = numer.parse().unwrap_or_default();
+    let denom: BigInt = denom.parse().unwrap_or_default();
+    if denom.is_zero() {
+        return Err(invalid(input, rest));
+    }
+    let numer = if negative { -numer } else { numer };
+    Ok((rest, Num::from_ratio(BigRational::new(numer, denom))))
+}
+
+/// A fixed-point decimal, `12.50m` or `-3m`.
+fn fixed(input: &str) -> IResult<&str, Num> {
+    let (rest, (negative, int_part, frac_part)) = terminated(
+        tuple((
+            sign,
+            opt(digits(10)),
+            opt(preceded(char('.'), opt(digits(10)))),
+        )),
+        one_of("mM"),
+    )(input)?;
+    let literal = format!(
+        "{}{}.{}",
+        if negative { "-" } else { "" },
+        int_part.unwrap_or_default(),
+        frac_part.flatten().unwrap_or_default()
+    );
+    match Decimal::from_str_exact(literal.trim_end_matches('.')) {
+        Ok(d) => Ok((rest, Num::Decimal(d))),
+        Err(_) => Err(invalid(input, rest)),
+    }
+}
+
+/// `inf` and `nan`, with an optional sign.
+fn special(input: &str) -> IResult<&str, Num> {
+    let (rest, (negative, value)) = pair(
+        sign,
+        alt((
+            value(f64::INFINITY, tag_no_case("inf")),
+            value(f64::NAN, tag_no_case("nan")),
+        )),
+    )(input)?;
+    Ok((rest, Num::Double(if negative { -value } else { value })))
+}
+
+/// Decimal integers and floats. A literal without a fractional part and
+/// with a non-negative exponent, like `12` or `15e3`, is an integer.
+fn decimal(input: &str) -> IResult<&str, Num> {
+    let (rest, (negative, int_part, frac_part, exponent)) = tuple((
+        sign,
+        opt(digits(10)),
+        opt(preceded(char('.'), opt(digits(10)))),
+        opt(preceded(one_of("eE"), pair(sign, digits(10)))),
+    ))(input)?;
+    let fraction_digits = frac_part.clone().flatten();
+    if int_part.is_none() && fraction_digits.is_none() {
+        return Err(nom::Err::Error(nom::error::Error::new(
+            input,
+            nom::error::ErrorKind::Digit,
+        )));
+    }
+    let sign = if negative { "-" } else { "" };
+    let mantissa = int_part.unwrap_or_default();
+    let exponent = exponent.map(|(neg, exp)| if neg { format!("-{}", exp) } else { exp });
+    let exp = exponent.as_deref().unwrap_or("0");
+    if frac_part.is_none() && !exp.starts_with('-') {
+        return match integer(&format!("{}{}", sign, mantissa), exp) {
+            Some(i) => Ok((rest, Num::from_big(i))),
+            None => Err(invalid(input, rest)),
+        };
+    }
+    let literal = format!(
+        "{}{}.{}e{}",
+        sign,
+        mantissa,
+        fraction_digits.unwrap_or_default(),
+        exp
+    );
+    Ok((rest, Num::Double(literal.parse().unwrap_or(f64::NAN))))
+}
+
+/// Largest exponent of an integer literal. Beyond it, literals like `1e99999`
+/// are invalid instead of allocating huge integers.
+const MAX_EXPONENT: u32 = 1000;
+
+/// `mantissa * 10^exp` as an integer, if exponent is non-negative and at
+/// most [`MAX_EXPONENT`].
+fn integer(mantissa: &str, exp: &str) -> Option<BigInt> {
+    let mantissa: BigInt = mantissa.parse().ok()?;
+    let exp: u32 = exp.parse().ok().filter(|&e| e <= MAX_EXPONENT)?;
+    Some(mantissa * BigInt::from(10).pow(exp))
+}
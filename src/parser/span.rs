@@ -0,0 +1,92 @@
+use std::fmt;
+
+/// A location in a source text. `line` and `column` start at 1 and the
+/// column counts characters, not bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Position {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// The region of source text a parsed node was read from, `end` excluded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
+
+/// Spans of a parsed `Expr`, mirroring its shape: the children of a list
+/// are the spans of its elements, in order, and atoms have no children.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SpanTree {
+    pub span: Span,
+    pub children: Vec<SpanTree>,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Byte offsets of the start of each line of a source text, used to turn
+/// offsets into line/column positions.
+pub(super) struct LineIndex<'a> {
+    source: &'a str,
+    starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub(super) fn new(source: &'a str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        LineIndex { source, starts }
+    }
+
+    pub(super) fn source(&self) -> &'a str {
+        self.source
+    }
+
+    /// Byte offset of the start of `line`, counted from 1.
+    pub(super) fn line_start(&self, line: usize) -> usize {
+        self.starts[line - 1]
+    }
+
+    pub(super) fn position(&self, offset: usize) -> Position {
+        let line = match self.starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next) => next - 1,
+        };
+        let start = self.starts[line];
+        Position {
+            offset,
+            line: line + 1,
+            column: self.source[start..offset].chars().count() + 1,
+        }
+    }
+}
+
+/// Spans recorded while parsing. Nom parsers only see the remaining input,
+/// so positions are stored as the length of the input left at that point
+/// and resolved against the full source once parsing is done.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct RawSpans {
+    pub(super) start: usize,
+    pub(super) end: usize,
+    pub(super) children: Vec<RawSpans>,
+}
+
+impl RawSpans {
+    pub(super) fn resolve(&self, index: &LineIndex) -> SpanTree {
+        let len = index.source.len();
+        SpanTree {
+            span: Span {
+                start: index.position(len - self.start),
+                end: index.position(len - self.end),
+            },
+            children: self.children.iter().map(|c| c.resolve(index)).collect(),
+        }
+    }
+}
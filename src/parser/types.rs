@@ -1,17 +1,35 @@
+use crate::evaluator::Function;
+use num_bigint::BigInt;
+use num_complex::Complex64;
+use num_rational::BigRational;
+use num_traits::ToPrimitive;
+use rust_decimal::Decimal;
 use std::fmt;
+use std::rc::Rc;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expr {
     Atomic(Atom),
-    Qexpr(Vec<Expr>),
+    /// `'x`: evaluates to `x` as data
+    Qexpr(Box<Expr>),
+    /// `` `x ``: like a quote, but `,` and `,@` inside evaluate their operand
+    Quasiquote(Box<Expr>),
+    /// `,x`: evaluate `x` inside a quasiquote
+    Unquote(Box<Expr>),
+    /// `,@x`: evaluate the list `x` and splice its elements in the enclosing
+    /// list of a quasiquote
+    UnquoteSplicing(Box<Expr>),
     List(Vec<Expr>),
+    /// A function as a value, e.g. the closure made by a `lambda`
+    Function(Rc<Function>),
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Atom {
     Name(String),
+    /// A symbol as a value, e.g. the result of evaluating `'foo`
+    Symbol(String),
     Quoted(String),
-    Op(Ops),
     Number(Num),
     Boolean(Bool),
 }
@@ -22,32 +40,86 @@ pub enum Bool {
     Nil,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Num {
     Double(f64),
     Int(i64),
+    /// An integer too large for `Int`, see [`Num::from_big`]
+    Big(BigInt),
+    /// An exact fraction in lowest terms, never an integer, see
+    /// [`Num::from_ratio`]
+    Ratio(BigRational),
+    /// A complex number, e.g. `3+4i`. The parts are always floats.
+    Complex(Complex64),
+    /// A fixed-point decimal, e.g. `12.50m`, which keeps its scale
+    Decimal(Decimal),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum Ops {
-    Sub,
-    Mul,
-    Div,
-    Add,
-    Rem,
-    Defun,
-    Nth,
-    List,
-    Eval,
-    Car,
-    Map,
+impl Num {
+    /// An exact integer, as an `Int` whenever it fits so that each integer
+    /// has a single representation.
+    pub fn from_big(big: BigInt) -> Num {
+        match big.to_i64() {
+            Some(i) => Num::Int(i),
+            None => Num::Big(big),
+        }
+    }
+
+    /// The nearest float to a real number, the real part of a complex one.
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Num::Double(d) => *d,
+            Num::Int(i) => *i as f64,
+            Num::Big(b) => b.to_f64().unwrap_or(f64::NAN),
+            Num::Ratio(r) => r.to_f64().unwrap_or(f64::NAN),
+            Num::Complex(c) => c.re,
+            Num::Decimal(d) => d.to_f64().unwrap_or(f64::NAN),
+        }
+    }
+
+    /// An exact rational, as an integer when its denominator is 1.
+    pub fn from_ratio(ratio: BigRational) -> Num {
+        if ratio.is_integer() {
+            Num::from_big(ratio.to_integer())
+        } else {
+            Num::Ratio(ratio)
+        }
+    }
+}
+
+fn fmt_double(d: f64, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    if d.is_nan() {
+        return write!(f, "nan");
+    }
+    // Keep a `.` so that the output reads back as a float, e.g. `3.0` rather
+    // than `3`, and `1.0e300` rather than `1e300`
+    let repr = format!("{:?}", d);
+    match repr.find('e') {
+        Some(e) if !repr.contains('.') => write!(f, "{}.0{}", &repr[..e], &repr[e..]),
+        _ => write!(f, "{}", repr),
+    }
 }
 
 impl fmt::Display for Num {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Num::Double(d) => write!(f, "{}", d),
+            Num::Double(d) => fmt_double(*d, f),
+            Num::Complex(c) => {
+                // The imaginary part always has a sign, e.g. `3.0+4.0i`
+                fmt_double(c.re, f)?;
+                if c.im.is_sign_negative() && !c.im.is_nan() {
+                    write!(f, "-")?;
+                    fmt_double(-c.im, f)?;
+                } else {
+                    write!(f, "+")?;
+                    fmt_double(c.im, f)?;
+                }
+                write!(f, "i")
+            }
             Num::Int(d) => write!(f, "{}", d),
+            Num::Big(b) => write!(f, "{}", b),
+            Num::Ratio(r) => write!(f, "{}/{}", r.numer(), r.denom()),
+            Num::Decimal(d) => write!(f, "{}m", d),
         }
     }
 }
@@ -64,9 +136,8 @@ impl fmt::Display for Bool {
 impl fmt::Display for Atom {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Atom::Name(n) => write!(f, "{}", n),
+            Atom::Name(n) | Atom::Symbol(n) => write!(f, "{}", n),
             Atom::Quoted(n) => write!(f, "\"{}\"", n),
-            Atom::Op(op) => write!(f, "{}", op),
             Atom::Number(num) => write!(f, "{}", num),
             Atom::Boolean(bl) => write!(f, "{}", bl),
         }
@@ -77,15 +148,11 @@ impl fmt::Display for Expr {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Expr::Atomic(atom) => write!(f, "{}", atom),
-            Expr::Qexpr(exprs) => write!(
-                f,
-                "'({})",
-                exprs
-                    .iter()
-                    .map(|x| format!("{}", x))
-                    .collect::<Vec<String>>()
-                    .join(" ")
-            ),
+            Expr::Qexpr(expr) => write!(f, "'{}", expr),
+            Expr::Quasiquote(expr) => write!(f, "`{}", expr),
+            Expr::Unquote(expr) => write!(f, ",{}", expr),
+            Expr::UnquoteSplicing(expr) => write!(f, ",@{}", expr),
+            Expr::Function(function) => write!(f, "{}", function),
             Expr::List(exprs) => write!(
                 f,
                 "({})",
@@ -98,9 +165,3 @@ impl fmt::Display for Expr {
         }
     }
 }
-
-impl fmt::Display for Ops {
-    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        write!(formatter, "{}", format!("{:?}", self).to_lowercase())
-    }
-}